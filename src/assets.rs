@@ -0,0 +1,89 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::settings::AssetSettings;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The fingerprinted path of each asset, indexed by its original path.
+pub type Manifest = BTreeMap<String, String>;
+
+/// Checks whether a file of the source directory is an asset.
+pub fn is_asset(settings: &AssetSettings, relpath: &Path) -> bool {
+    settings
+        .directories
+        .iter()
+        .any(|directory| relpath.starts_with(directory.trim_matches('/')))
+}
+
+/// Returns the SHA-256 hash of a content, in hexadecimal.
+pub fn content_hash(content: &[u8]) -> String {
+    let mut hash = String::with_capacity(64);
+    for byte in Sha256::digest(content).iter() {
+        write!(hash, "{:02x}", byte).unwrap();
+    }
+    hash
+}
+
+/// Returns the path of an asset including a hash of its content, such as
+/// `css/custom.0a1b2c3d.css`.
+pub fn fingerprint(relpath: &Path, content: &[u8], length: usize) -> PathBuf {
+    let mut hash = content_hash(content);
+    hash.truncate(length.max(1));
+
+    let stem = relpath
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let name = match relpath.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, hash, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, hash),
+    };
+    relpath.with_file_name(name)
+}
+
+/// Converts a relative path to the path of an URL.
+pub fn url_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[test]
+fn test_is_asset() {
+    let settings = AssetSettings::default();
+    assert!(is_asset(&settings, Path::new("css/custom.css")));
+    assert!(is_asset(&settings, Path::new("img/logo/small.png")));
+    assert!(!is_asset(&settings, Path::new("cssfoo/custom.css")));
+    assert!(!is_asset(&settings, Path::new("robots.txt")));
+}
+
+#[test]
+fn test_fingerprint() {
+    let path = fingerprint(Path::new("css/custom.css"), b"body {}", 8);
+    assert_eq!(url_path(&path), "css/custom.62368a1a.css");
+    assert_ne!(
+        fingerprint(Path::new("css/custom.css"), b"body { }", 8),
+        path
+    );
+    assert_eq!(
+        url_path(&fingerprint(Path::new("LICENSE"), b"", 4)),
+        "LICENSE.e3b0"
+    );
+}
@@ -0,0 +1,238 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::sources::build_matcher;
+use super::{Error, Result, Settings};
+use ignore::gitignore::Gitignore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The list of the files written by the previous build, relative to the
+/// cache directory.
+pub const OUTPUT_MANIFEST: &str = "output.json";
+
+/// The files of the output directory always kept, before the patterns of
+/// `compiler.keep`.
+pub const DEFAULT_KEEP: &[&str] = &[".git", "CNAME"];
+
+/// The files written to the output directory by a build.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputManifest {
+    pub output_dir: PathBuf,
+    /// The paths of the files, relative to the output directory
+    pub files: BTreeSet<String>,
+}
+
+impl OutputManifest {
+    /// Loads the manifest of the previous build, if it wrote to the same
+    /// output directory.
+    pub fn load(path: &Path, output_dir: &Path) -> OutputManifest {
+        fs::read(path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<OutputManifest>(&content).ok())
+            .filter(|manifest| manifest.output_dir == output_dir)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// Builds the matcher of the files of the output directory which are never
+/// removed.
+pub fn keep_matcher(settings: &Settings) -> Result<Gitignore> {
+    build_matcher(
+        &settings.output_dir,
+        "compiler.keep",
+        DEFAULT_KEEP,
+        &settings.keep,
+    )?
+    .build()
+    .map_err(|err| Error::Settings {
+        message: format!("invalid pattern in `compiler.keep`: {}", err),
+    })
+}
+
+fn is_kept(keep: &Gitignore, relpath: &Path, is_dir: bool) -> bool {
+    keep.matched_path_or_any_parents(relpath, is_dir)
+        .is_ignore()
+}
+
+/// Removes the content of the output directory, except the kept files.
+pub fn clean(settings: &Settings) -> Result<()> {
+    let output_dir = &settings.output_dir;
+    if !output_dir.is_dir() {
+        return Ok(());
+    }
+    let contains_sources = match (
+        fs::canonicalize(output_dir),
+        fs::canonicalize(&settings.source_dir),
+    ) {
+        (Ok(output_dir), Ok(source_dir)) => source_dir.starts_with(output_dir),
+        _ => true,
+    };
+    if contains_sources {
+        return Err(Error::Settings {
+            message: format!(
+                "{} contains the source directory and cannot be cleaned",
+                output_dir.display()
+            ),
+        });
+    }
+
+    let keep = keep_matcher(settings)?;
+    clean_dir(output_dir, Path::new(""), &keep).map_err(|err| Error::Output {
+        dest: output_dir.clone(),
+        cause: Box::new(err),
+    })
+}
+
+fn clean_dir(output_dir: &Path, reldir: &Path, keep: &Gitignore) -> io::Result<()> {
+    for entry in fs::read_dir(output_dir.join(reldir))? {
+        let entry = entry?;
+        let relpath = reldir.join(entry.file_name());
+        let is_dir = entry.file_type()?.is_dir();
+        if is_kept(keep, &relpath, is_dir) {
+            continue;
+        }
+        if is_dir {
+            clean_dir(output_dir, &relpath, keep)?;
+            // The directory is left when it contains kept files
+            let _ = fs::remove_dir(entry.path());
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the files written by the previous build which were not written
+/// again, along with the directories left empty. Returns the number of
+/// removed files.
+pub fn remove_stale_files(
+    output_dir: &Path,
+    previous: &OutputManifest,
+    current: &OutputManifest,
+    keep: &Gitignore,
+) -> usize {
+    let mut removed = 0;
+    for file in previous.files.difference(&current.files) {
+        let relpath = Path::new(file);
+        if is_kept(keep, relpath, false) {
+            continue;
+        }
+        match fs::remove_file(output_dir.join(relpath)) {
+            Ok(()) => {
+                debug!("Removing {}", file);
+                removed += 1;
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => warn!("Could not remove {}: {}", file, err),
+        }
+
+        let mut parent = relpath.parent();
+        while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+            if fs::remove_dir(output_dir.join(dir)).is_err() {
+                break;
+            }
+            parent = dir.parent();
+        }
+    }
+    removed
+}
+
+#[test]
+fn test_remove_stale_files() {
+    let dir = crate::utils::TempDir::new("cleanup");
+    for file in [
+        "index.html",
+        "old/page.html",
+        "blog/old.html",
+        "blog/post.html",
+        "CNAME",
+    ] {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    let settings = Settings {
+        output_dir: dir.to_path_buf(),
+        keep: vec!["*.txt".into()],
+        ..Settings::default()
+    };
+    let keep = keep_matcher(&settings).unwrap();
+    let manifest = |files: &[&str]| OutputManifest {
+        output_dir: dir.to_path_buf(),
+        files: files.iter().map(|file| file.to_string()).collect(),
+    };
+    let previous = manifest(&[
+        "index.html",
+        "old/page.html",
+        "blog/old.html",
+        "blog/post.html",
+        "CNAME",
+        "gone.html",
+    ]);
+    let current = manifest(&["index.html", "blog/post.html"]);
+    let removed = remove_stale_files(&dir, &previous, &current, &keep);
+
+    let exists = |file: &str| dir.join(file).exists();
+    assert_eq!(removed, 2);
+    assert!(exists("index.html"));
+    assert!(exists("blog/post.html"));
+    assert!(exists("CNAME"));
+    assert!(!exists("old"));
+    assert!(!exists("blog/old.html"));
+}
+
+#[test]
+fn test_clean() {
+    let dir = crate::utils::TempDir::new("clean");
+    let (source_dir, output_dir) = (dir.join("source"), dir.join("output"));
+    fs::create_dir_all(&source_dir).unwrap();
+    for file in ["index.html", "blog/post.html", ".git/HEAD", "CNAME"] {
+        let path = output_dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    let settings = Settings {
+        source_dir: source_dir.clone(),
+        output_dir: output_dir.clone(),
+        ..Settings::default()
+    };
+    assert!(clean(&settings).is_ok());
+    assert!(clean(&Settings {
+        output_dir: dir.to_path_buf(),
+        ..settings.clone()
+    })
+    .is_err());
+
+    let mut remaining: Vec<String> = fs::read_dir(&output_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into())
+        .collect();
+    remaining.sort();
+    assert_eq!(remaining, [".git", "CNAME"]);
+}
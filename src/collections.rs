@@ -0,0 +1,229 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::document::DocumentMetadata;
+use super::sections;
+use super::settings::{CollectionSettings, SectionSettings};
+use super::Settings;
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub type Collections = BTreeMap<String, Vec<Arc<DocumentMetadata>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+}
+
+/// A condition on a metadata field of the documents, such as `draft != true`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub field: String,
+    pub operator: Operator,
+    pub value: String,
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Condition, String> {
+        let (index, operator) = match (input.find("!="), input.find("==")) {
+            (Some(index), _) => (index, Operator::NotEqual),
+            (None, Some(index)) => (index, Operator::Equal),
+            (None, None) => {
+                return Err(format!(
+                    "\"{}\" is not a valid condition, expected `field == value` \
+                     or `field != value`",
+                    input
+                ))
+            }
+        };
+
+        let field = input[..index].trim().to_lowercase();
+        let value = input[index + 2..]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+        if field.is_empty() {
+            return Err(format!("\"{}\" does not name a metadata field", input));
+        }
+
+        Ok(Condition {
+            field,
+            operator,
+            value: value.to_lowercase(),
+        })
+    }
+}
+
+impl Condition {
+    /// Checks whether a document fulfills this condition. List fields such as
+    /// `keywords` are equal to a value when they contain it.
+    pub fn matches(&self, document: &DocumentMetadata) -> bool {
+        let values: Vec<String> = match self.field.as_str() {
+            "title" => vec![document.title.clone()],
+            "url" => vec![document.url.clone()],
+            "language" => document.language.iter().cloned().collect(),
+            "keywords" => document.keywords.clone(),
+            name => document
+                .extra
+                .get(name)
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+        };
+
+        let found = values
+            .iter()
+            .any(|value| value.trim().to_lowercase() == self.value);
+        match self.operator {
+            Operator::Equal => found,
+            Operator::NotEqual => !found,
+        }
+    }
+}
+
+/// Compiles the pattern matching the sources of the documents of a collection.
+pub fn compile_pattern(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+}
+
+fn collect_one(
+    collection: &CollectionSettings,
+    documents: &[Arc<DocumentMetadata>],
+) -> Vec<Arc<DocumentMetadata>> {
+    let matcher = match compile_pattern(&collection.pattern) {
+        Ok(matcher) => matcher,
+        Err(err) => {
+            error!("{}", err);
+            return Vec::new();
+        }
+    };
+
+    let mut selected: Vec<Arc<DocumentMetadata>> = documents
+        .iter()
+        .filter(|document| matcher.is_match(&document.source))
+        .filter(|document| {
+            collection
+                .filters
+                .iter()
+                .all(|condition| condition.matches(document))
+        })
+        .cloned()
+        .collect();
+
+    sections::sort(
+        &mut selected,
+        SectionSettings {
+            sort_by: collection.sort_by,
+            order: collection.order,
+            ..SectionSettings::default()
+        },
+    );
+    selected
+}
+
+/// Selects and sorts the documents of each collection declared in the
+/// settings.
+pub fn collect(settings: &Settings, documents: &[Arc<DocumentMetadata>]) -> Collections {
+    settings
+        .collections
+        .iter()
+        .map(|(name, collection)| (name.clone(), collect_one(collection, documents)))
+        .collect()
+}
+
+#[test]
+fn test_condition() {
+    let mut document = DocumentMetadata {
+        keywords: vec!["Rust".into(), "web".into()],
+        ..DocumentMetadata::default()
+    };
+    let matches = |condition: &str, document: &DocumentMetadata| {
+        condition.parse::<Condition>().unwrap().matches(document)
+    };
+
+    assert!(matches("draft != true", &document));
+    assert!(!matches("draft == true", &document));
+    assert!(matches("keywords == rust", &document));
+    assert!(matches("keywords != python", &document));
+
+    document.extra.insert("draft".into(), "True".into());
+    assert!(!matches("draft != true", &document));
+    assert!(matches("Draft == \"true\"", &document));
+
+    assert!("draft".parse::<Condition>().is_err());
+    assert!("== true".parse::<Condition>().is_err());
+}
+
+#[test]
+fn test_collect() {
+    use crate::settings::{SortKey, SortOrder};
+    use std::path::PathBuf;
+
+    let document = |source: &str, title: &str, draft: bool| {
+        let mut metadata = DocumentMetadata {
+            url: source.replace(".md", ".html"),
+            source: PathBuf::from(source),
+            title: title.into(),
+            ..DocumentMetadata::default()
+        };
+        if draft {
+            metadata.extra.insert("draft".into(), "true".into());
+        }
+        Arc::new(metadata)
+    };
+    let documents = vec![
+        document("blog/2020/b.md", "B", false),
+        document("blog/a.md", "A", false),
+        document("blog/c.md", "C", true),
+        document("about.md", "About", false),
+    ];
+
+    let mut settings = Settings::default();
+    settings.collections.insert(
+        "posts".into(),
+        CollectionSettings {
+            pattern: "blog/**/*.md".into(),
+            filters: vec!["draft != true".parse().unwrap()],
+            sort_by: SortKey::Title,
+            order: SortOrder::Ascending,
+            ..CollectionSettings::default()
+        },
+    );
+    settings.collections.insert(
+        "pages".into(),
+        CollectionSettings {
+            pattern: "*.md".into(),
+            ..CollectionSettings::default()
+        },
+    );
+
+    let collections = collect(&settings, &documents);
+    let titles = |name: &str| -> Vec<String> {
+        collections[name]
+            .iter()
+            .map(|document| document.title.clone())
+            .collect()
+    };
+    assert_eq!(titles("posts"), ["A", "B"]);
+    assert_eq!(titles("pages"), ["About"]);
+}
@@ -0,0 +1,162 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::utils;
+use super::{Error, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+use std::{error, result};
+use walkdir::WalkDir;
+
+type BoxedError = Box<dyn error::Error + Send + Sync>;
+
+/// Parses the content of a data file according to its extension, or returns
+/// `None` when the format is unknown.
+fn parse_data(extension: &str, content: &str) -> result::Result<Option<Value>, BoxedError> {
+    let value = match extension {
+        "toml" => utils::toml_to_json(&toml::from_str(content)?),
+        "json" => serde_json::from_str(content)?,
+        "yaml" | "yml" => serde_yaml::from_str(content)?,
+        "csv" => {
+            let rows = csv::Reader::from_reader(content.as_bytes())
+                .deserialize()
+                .collect::<result::Result<Vec<BTreeMap<String, String>>, csv::Error>>()?;
+            serde_json::to_value(rows)?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Inserts a value at the path of a data file, creating the parent objects.
+fn insert_value(data: &mut Map<String, Value>, relpath: &Path, value: Value) {
+    let relpath = relpath.with_extension("");
+    let mut keys: Vec<String> = relpath
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str().map(String::from),
+            _ => None,
+        })
+        .collect();
+
+    let last = match keys.pop() {
+        Some(last) => last,
+        None => return,
+    };
+
+    let mut object = data;
+    for key in keys {
+        let child = object
+            .entry(key)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !child.is_object() {
+            *child = Value::Object(Map::new());
+        }
+        object = child.as_object_mut().unwrap();
+    }
+
+    if object.insert(last, value).is_some() {
+        warn!("Data file {} overrides another one", relpath.display());
+    }
+}
+
+/// Reads all the data files of a directory.
+///
+/// Each file is exposed under its path relative to the directory, without its
+/// extension: `_data/team/members.yaml` becomes `data.team.members`.
+pub fn load_data(dir: &Path) -> Result<Value> {
+    let mut data = Map::new();
+
+    if !dir.is_dir() {
+        return Ok(Value::Object(data));
+    }
+
+    let entries = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| utils::is_public(&entry.path()));
+
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::Data {
+            path: err.path().unwrap_or(dir).into(),
+            cause: Box::new(err),
+        })?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let value = fs::read_to_string(path)
+            .map_err(|err| Box::new(err) as BoxedError)
+            .and_then(|content| parse_data(&extension, &content))
+            .map_err(|cause| Error::Data {
+                path: path.into(),
+                cause,
+            })?;
+
+        match value {
+            Some(value) => insert_value(&mut data, path.strip_prefix(dir).unwrap(), value),
+            None => warn!("Ignoring data file {}: unknown format", path.display()),
+        }
+    }
+
+    Ok(Value::Object(data))
+}
+
+#[test]
+fn test_parse_data() {
+    let parse = |extension: &str, content: &str| parse_data(extension, content).unwrap();
+
+    assert_eq!(
+        parse("toml", "name = \"Foo\"\nage = 3"),
+        Some(serde_json::json!({"name": "Foo", "age": 3}))
+    );
+    assert_eq!(parse("json", "[1, 2]"), Some(serde_json::json!([1, 2])));
+    assert_eq!(
+        parse("yaml", "- name: Foo\n  tags: [a, b]"),
+        Some(serde_json::json!([{"name": "Foo", "tags": ["a", "b"]}]))
+    );
+    assert_eq!(
+        parse("csv", "name,role\nFoo,dev\nBar,ops\n"),
+        Some(serde_json::json!([
+            {"name": "Foo", "role": "dev"},
+            {"name": "Bar", "role": "ops"}
+        ]))
+    );
+    assert_eq!(parse("txt", "foo"), None);
+    assert!(parse_data("json", "{").is_err());
+}
+
+#[test]
+fn test_insert_value() {
+    let mut data = Map::new();
+    insert_value(&mut data, Path::new("team.toml"), Value::from(1));
+    insert_value(&mut data, Path::new("links/social.yaml"), Value::from(2));
+    insert_value(&mut data, Path::new("links/friends.csv"), Value::from(3));
+    assert_eq!(
+        Value::Object(data),
+        serde_json::json!({"team": 1, "links": {"social": 2, "friends": 3}})
+    );
+}
@@ -0,0 +1,180 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::settings::DatesSource;
+use super::utils::DateTime;
+use super::{DocumentMetadata, Settings};
+use chrono::{self, FixedOffset, NaiveDate, TimeZone};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Fills the creation and modification dates missing from the metadata of a
+/// document.
+///
+/// The creation date is read from a `YYYY-MM-DD-` prefix in the file name,
+/// then from the source configured with `compiler.dates_from`.
+pub fn fill_missing_dates(metadata: &mut DocumentMetadata, path: &Path, settings: &Settings) {
+    if metadata.created.is_none() {
+        metadata.created = date_from_file_name(path, &settings.timezone);
+    }
+
+    if metadata.created.is_some() && metadata.modified.is_some() {
+        return;
+    }
+
+    let (created, modified) = match settings.dates_from {
+        None => return,
+        Some(DatesSource::Filesystem) => filesystem_dates(path, &settings.timezone),
+        Some(DatesSource::Git) => git_dates(path),
+    };
+
+    if metadata.created.is_none() {
+        metadata.created = created;
+    }
+    if metadata.modified.is_none() {
+        metadata.modified = modified;
+    }
+}
+
+/// Reads the date prefix of a file name such as `2020-05-01-my-post.md`.
+pub fn date_from_file_name(path: &Path, timezone: &FixedOffset) -> Option<DateTime> {
+    let file_name = path.file_name()?.to_str()?;
+    let prefix = file_name.get(..11)?;
+
+    if !prefix.ends_with('-') {
+        return None;
+    }
+
+    NaiveDate::parse_from_str(&prefix[..10], "%Y-%m-%d")
+        .ok()
+        .and_then(|date| {
+            timezone
+                .from_local_datetime(&date.and_hms(0, 0, 0))
+                .single()
+        })
+        .map(DateTime::from)
+}
+
+fn filesystem_dates(path: &Path, timezone: &FixedOffset) -> (Option<DateTime>, Option<DateTime>) {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            debug!("Could not read the metadata of {}: {}", path.display(), err);
+            return (None, None);
+        }
+    };
+
+    let to_date = |time: SystemTime| {
+        DateTime::from(chrono::DateTime::<chrono::Utc>::from(time).with_timezone(timezone))
+    };
+
+    let modified = metadata.modified().ok().map(to_date);
+    let created = metadata
+        .created()
+        .ok()
+        .map(to_date)
+        .or_else(|| modified.clone());
+
+    (created, modified)
+}
+
+fn git_dates(path: &Path) -> (Option<DateTime>, Option<DateTime>) {
+    let (dir, file_name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(file_name)) => (dir, file_name),
+        _ => return (None, None),
+    };
+
+    let output = Command::new("git")
+        .current_dir(if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        })
+        .args(["log", "--follow", "--format=%aI", "--"])
+        .arg(file_name)
+        .output();
+
+    let stdout = match output {
+        Ok(ref output) if output.status.success() => String::from_utf8_lossy(&output.stdout),
+        Ok(ref output) => {
+            debug!(
+                "git log failed for {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return (None, None);
+        }
+        Err(err) => {
+            debug!("Could not run git: {}", err);
+            return (None, None);
+        }
+    };
+
+    // Commits are listed from the most recent to the oldest one.
+    let dates: Vec<DateTime> = stdout
+        .lines()
+        .filter_map(|line| DateTime::from_string(line.trim()))
+        .collect();
+
+    (dates.last().cloned(), dates.first().cloned())
+}
+
+#[test]
+fn test_date_from_file_name() {
+    let utc = FixedOffset::east(0);
+    assert_eq!(
+        date_from_file_name(Path::new("blog/2020-05-01-my-post.md"), &utc),
+        DateTime::from_string("2020-05-01T00:00:00+00:00")
+    );
+    assert_eq!(
+        date_from_file_name(Path::new("blog/2020-05-01.md"), &utc),
+        None
+    );
+    assert_eq!(date_from_file_name(Path::new("my-post.md"), &utc), None);
+    assert_eq!(
+        date_from_file_name(Path::new("2020-13-01-my-post.md"), &utc),
+        None
+    );
+}
+
+#[test]
+fn test_fill_missing_dates() {
+    let created = DateTime::from_string("2019-01-01T00:00:00+00:00");
+    let mut metadata = DocumentMetadata {
+        created: created.clone(),
+        ..DocumentMetadata::default()
+    };
+    fill_missing_dates(
+        &mut metadata,
+        Path::new("2020-05-01-my-post.md"),
+        &Settings::default(),
+    );
+    assert_eq!(metadata.created, created);
+    assert_eq!(metadata.modified, None);
+
+    let mut metadata = DocumentMetadata::default();
+    fill_missing_dates(
+        &mut metadata,
+        Path::new("2020-05-01-my-post.md"),
+        &Settings::default(),
+    );
+    assert_eq!(
+        metadata.created,
+        DateTime::from_string("2020-05-01T00:00:00+00:00")
+    );
+}
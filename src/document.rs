@@ -14,18 +14,92 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::Result;
-use crate::metadata::{Date, Field, Keywords};
+use super::{Result, Settings};
+use crate::metadata::{read_metadata_list, Date, Field, Keywords, Value};
 use crate::utils::DateTime;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::iter::FromIterator;
-use std::rc::Rc;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-const CREATED_FIELD: &dyn Field = &Date("created") as &dyn Field;
-const MODIFIED_FIELD: &dyn Field = &Date("modified") as &dyn Field;
 const KEYWORDS_FIELD: &dyn Field = &Keywords("keywords") as &dyn Field;
 
+/// A link to a translation of a document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    pub language: String,
+    pub url: String,
+    pub title: String,
+}
+
+impl<'a> From<&'a DocumentMetadata> for Translation {
+    fn from(metadata: &'a DocumentMetadata) -> Translation {
+        Translation {
+            language: metadata.language.clone().unwrap_or_default(),
+            url: metadata.url.clone(),
+            title: metadata.title.clone(),
+        }
+    }
+}
+
+/// A link to another document of the website.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentLink {
+    pub title: String,
+    pub url: String,
+    pub created: Option<DateTime>,
+}
+
+impl<'a> From<&'a DocumentMetadata> for DocumentLink {
+    fn from(metadata: &'a DocumentMetadata) -> DocumentLink {
+        DocumentLink {
+            title: metadata.title.clone(),
+            url: metadata.url.clone(),
+            created: metadata.created.clone(),
+        }
+    }
+}
+
+/// A short excerpt of a document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub html: String,
+    pub text: String,
+}
+
+impl Summary {
+    /// Builds a summary from a plain text, shortened to its first `words`
+    /// words.
+    pub fn from_text(text: &str, words: usize) -> Summary {
+        let mut split = text.split_whitespace();
+        let mut text = split.by_ref().take(words).collect::<Vec<&str>>().join(" ");
+        if split.next().is_some() {
+            text.push('…');
+        }
+
+        Summary {
+            html: format!("<p>{}</p>", handlebars::html_escape(&text)),
+            text,
+        }
+    }
+}
+
+/// Returns the number of minutes needed to read `words` words, rounded up.
+pub fn reading_time(words: usize, words_per_minute: usize) -> usize {
+    match words_per_minute {
+        0 => 0,
+        speed => words.div_ceil(speed),
+    }
+}
+
+/// A link to the index of a parent directory of a document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Breadcrumb {
+    pub title: String,
+    pub url: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMetadata {
     pub url: String,
@@ -34,6 +108,42 @@ pub struct DocumentMetadata {
     pub modified: Option<DateTime>,
     pub created: Option<DateTime>,
     pub keywords: Vec<String>,
+    #[serde(default)]
+    pub translations: Vec<Translation>,
+    /// The previous document of the same section
+    #[serde(default)]
+    pub previous: Option<DocumentLink>,
+    /// The next document of the same section
+    #[serde(default)]
+    pub next: Option<DocumentLink>,
+    /// The documents sharing the most terms with this one
+    #[serde(default)]
+    pub related: Vec<DocumentLink>,
+    /// The indexes of the parent directories, from the root of the website
+    #[serde(default)]
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// An excerpt of the document
+    #[serde(default)]
+    pub summary: Option<Summary>,
+    /// The number of words of the document
+    #[serde(default)]
+    pub word_count: usize,
+    /// The number of minutes needed to read the document
+    #[serde(default)]
+    pub reading_time: usize,
+    /// The metadata fields without a dedicated attribute
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+    /// The path of the source of the document, relative to the source
+    /// directory
+    #[serde(skip)]
+    pub source: PathBuf,
+    /// The text of the document, without markup
+    #[serde(skip)]
+    pub text: String,
+    /// The text of the headings of the document
+    #[serde(skip)]
+    pub headings: Vec<String>,
 }
 
 impl Default for DocumentMetadata {
@@ -45,12 +155,24 @@ impl Default for DocumentMetadata {
             modified: None,
             created: None,
             keywords: Vec::new(),
+            translations: Vec::new(),
+            previous: None,
+            next: None,
+            related: Vec::new(),
+            breadcrumbs: Vec::new(),
+            summary: None,
+            word_count: 0,
+            reading_time: 0,
+            extra: BTreeMap::new(),
+            source: PathBuf::new(),
+            text: String::new(),
+            headings: Vec::new(),
         }
     }
 }
 
 impl DocumentMetadata {
-    pub fn from_raw<T>(raw: T) -> Result<DocumentMetadata>
+    pub fn from_raw<T>(raw: T, settings: &Settings) -> Result<DocumentMetadata>
     where
         T: Iterator<Item = (String, String)>,
     {
@@ -67,20 +189,47 @@ impl DocumentMetadata {
             metadata.language = Some(language.trim().into());
         }
 
+        if let Some(summary) = raw_metadata.remove("summary") {
+            metadata.summary = Some(Summary::from_text(&summary, usize::MAX));
+        }
+
         if let Some(keywords) = raw_metadata.remove("keywords") {
             metadata.keywords = KEYWORDS_FIELD.from_raw(keywords.as_ref())?.into();
         }
 
         if let Some(ref created) = raw_metadata.remove("created") {
-            metadata.created = CREATED_FIELD.from_raw(created)?.into();
+            metadata.created = Date::with_timezone("created", settings.timezone)
+                .from_raw(created)?
+                .into();
         }
 
         if let Some(ref modified) = raw_metadata.remove("modified") {
-            metadata.modified = MODIFIED_FIELD.from_raw(modified)?.into();
+            metadata.modified = Date::with_timezone("modified", settings.timezone)
+                .from_raw(modified)?
+                .into();
         }
 
+        metadata.extra = raw_metadata
+            .into_iter()
+            .map(|(key, value)| (key, value.trim().into()))
+            .collect();
+
         Ok(metadata)
     }
+
+    /// Returns the terms of a list field, such as `keywords` or a custom
+    /// `tags` field.
+    pub fn terms(&self, field: &str) -> Vec<String> {
+        if field == "keywords" {
+            self.keywords.clone()
+        } else {
+            self.extra
+                .get(field)
+                .and_then(|raw| read_metadata_list(raw).ok())
+                .map(Value::into)
+                .unwrap_or_default()
+        }
+    }
 }
 
 #[test]
@@ -92,7 +241,7 @@ fn test_from_raw() {
         ("keywords".into(), "foo, bar".into()),
     ];
 
-    let metadata = DocumentMetadata::from_raw(raw_metadata.into_iter());
+    let metadata = DocumentMetadata::from_raw(raw_metadata.into_iter(), &Settings::default());
     assert!(metadata.is_ok());
     if let Ok(result) = metadata {
         assert_eq!(result.title, "Foo bar");
@@ -100,6 +249,54 @@ fn test_from_raw() {
     }
 }
 
+#[test]
+fn test_extra_metadata() {
+    let raw_metadata: Vec<(String, String)> = vec![
+        ("Title".into(), "Foo bar".into()),
+        ("Tags".into(), " rust; web ".into()),
+    ];
+
+    let metadata =
+        DocumentMetadata::from_raw(raw_metadata.into_iter(), &Settings::default()).unwrap();
+    assert_eq!(metadata.extra.get("tags"), Some(&"rust; web".into()));
+    assert_eq!(metadata.terms("tags"), ["rust", "web"]);
+    assert!(metadata.terms("categories").is_empty());
+}
+
+#[test]
+fn test_summary() {
+    let summary = Summary::from_text("Fish & chips\nare  good", 3);
+    assert_eq!(summary.text, "Fish & chips…");
+    assert_eq!(summary.html, "<p>Fish &amp; chips…</p>");
+    assert_eq!(Summary::from_text("Fish", 3).text, "Fish");
+
+    assert_eq!(reading_time(0, 200), 0);
+    assert_eq!(reading_time(1, 200), 1);
+    assert_eq!(reading_time(401, 200), 3);
+}
+
+#[test]
+fn test_from_raw_with_timezone() {
+    let settings = Settings {
+        timezone: chrono::FixedOffset::east(2 * 3600),
+        ..Settings::default()
+    };
+    let raw_metadata: Vec<(String, String)> = vec![
+        ("created".into(), "2020-05-01".into()),
+        ("modified".into(), "2020-05-02 14:00".into()),
+    ];
+
+    let metadata = DocumentMetadata::from_raw(raw_metadata.into_iter(), &settings).unwrap();
+    assert_eq!(
+        metadata.created,
+        DateTime::from_string("2020-05-01T00:00:00+02:00")
+    );
+    assert_eq!(
+        metadata.modified,
+        DateTime::from_string("2020-05-02T14:00:00+02:00")
+    );
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum DocumentContent {
@@ -107,7 +304,11 @@ pub enum DocumentContent {
         content: String,
     },
     Index {
-        documents: Vec<Rc<DocumentMetadata>>,
+        documents: Vec<Arc<DocumentMetadata>>,
+    },
+    /// A file written as is, without a template
+    Raw {
+        raw: String,
     },
 }
 
@@ -117,10 +318,10 @@ impl From<String> for DocumentContent {
     }
 }
 
-impl FromIterator<Rc<DocumentMetadata>> for DocumentContent {
+impl FromIterator<Arc<DocumentMetadata>> for DocumentContent {
     fn from_iter<T>(documents: T) -> Self
     where
-        T: IntoIterator<Item = Rc<DocumentMetadata>>,
+        T: IntoIterator<Item = Arc<DocumentMetadata>>,
     {
         DocumentContent::Index {
             documents: documents.into_iter().collect(),
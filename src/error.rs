@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::borrow::Borrow;
+use crate::utils::DATE_FORMATS;
 use std::io::Error as IoError;
 use std::path::PathBuf;
 use std::{error, fmt, result};
@@ -27,31 +27,49 @@ pub enum Error {
     // An error happened while reading a document.
     Reader {
         path: PathBuf,
-        cause: Box<dyn error::Error>,
+        cause: Box<dyn error::Error + Send + Sync>,
     },
 
     // An error happened while rendering a file
     Render {
-        cause: Box<dyn error::Error>,
+        cause: Box<dyn error::Error + Send + Sync>,
     },
 
     // An error happened while copying a file
     Copy {
         from: PathBuf,
         to: PathBuf,
-        cause: Box<dyn error::Error>,
+        cause: Box<dyn error::Error + Send + Sync>,
     },
 
     // An error happened while writing an output file
     Output {
         dest: PathBuf,
-        cause: Box<dyn error::Error>,
+        cause: Box<dyn error::Error + Send + Sync>,
     },
 
     // An error happened while reading the configuration file
     LoadSettings {
         path: PathBuf,
-        cause: Box<dyn error::Error>,
+        cause: Box<dyn error::Error + Send + Sync>,
+    },
+
+    // An error happened while reading a data file
+    Data {
+        path: PathBuf,
+        cause: Box<dyn error::Error + Send + Sync>,
+    },
+
+    // A stylesheet could not be compiled
+    Stylesheet {
+        path: PathBuf,
+        message: String,
+    },
+
+    // An image could not be resized or converted
+    Image {
+        path: PathBuf,
+        cause: Box<dyn error::Error + Send + Sync>,
     },
 
     // The software is misconfigured
@@ -114,9 +132,24 @@ impl fmt::Display for Error {
                 path.display(),
                 cause
             ),
-            Error::InvalidDate { ref date } => {
-                write!(f, "\"{}\" is not a valid date.", date.trim())
-            }
+            Error::Data {
+                ref path,
+                ref cause,
+            } => write!(f, "Could not read data file {}: {}", path.display(), cause),
+            Error::Stylesheet {
+                ref path,
+                ref message,
+            } => write!(f, "Could not compile {}: {}", path.display(), message),
+            Error::Image {
+                ref path,
+                ref cause,
+            } => write!(f, "Could not process image {}: {}", path.display(), cause),
+            Error::InvalidDate { ref date } => write!(
+                f,
+                "\"{}\" is not a valid date. Accepted formats: {}.",
+                date.trim(),
+                DATE_FORMATS.join(", ")
+            ),
             Error::UnknownMetadataField { ref name } => write!(f, "Unknown metadata \"{}\".", name),
             Error::Settings { ref message } => write!(f, "{}", message),
         }
@@ -125,14 +158,16 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        match *self {
-            Error::Io(ref err) => Some(err),
-            Error::Reader { ref cause, .. } => Some(cause.borrow()),
-            Error::Copy { ref cause, .. } => Some(cause.borrow()),
-            Error::Output { ref cause, .. } => Some(cause.borrow()),
-            Error::Render { ref cause, .. } => Some(cause.borrow()),
-            Error::LoadSettings { ref cause, .. } => Some(cause.borrow()),
-            _ => None,
-        }
+        let cause: &(dyn error::Error + 'static) = match *self {
+            Error::Io(ref err) => err,
+            Error::Reader { ref cause, .. } => cause.as_ref(),
+            Error::Copy { ref cause, .. } => cause.as_ref(),
+            Error::Output { ref cause, .. } => cause.as_ref(),
+            Error::Render { ref cause, .. } => cause.as_ref(),
+            Error::LoadSettings { ref cause, .. } => cause.as_ref(),
+            Error::Data { ref cause, .. } => cause.as_ref(),
+            _ => return None,
+        };
+        Some(cause)
     }
 }
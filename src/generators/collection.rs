@@ -0,0 +1,95 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::collections::Collections;
+use super::super::sections;
+use super::super::{Document, DocumentContent, DocumentMetadata, Settings};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Generates an index of the documents of each collection declaring an
+/// `index` URL, from the collections already gathered for the templates.
+pub fn collection_indexes(
+    settings: &Settings,
+    collections: &Collections,
+    docs: &[Arc<DocumentMetadata>],
+) -> Vec<Arc<Document>> {
+    let titles: HashMap<String, String> = docs
+        .iter()
+        .map(|doc| (doc.url.trim_start_matches('/').into(), doc.title.clone()))
+        .collect();
+
+    collections
+        .iter()
+        .filter_map(|(name, documents)| {
+            let collection = &settings.collections[name];
+            let url = collection.index.as_ref()?.trim_start_matches('/');
+            let language = settings.site_language.clone();
+
+            let meta = DocumentMetadata {
+                breadcrumbs: sections::breadcrumbs(url, language.as_deref(), &titles, settings),
+                url: url.into(),
+                title: collection.title.clone().unwrap_or_else(|| name.clone()),
+                language,
+                ..DocumentMetadata::default()
+            };
+
+            Some(Arc::new(Document::new(
+                meta,
+                DocumentContent::Index {
+                    documents: documents.clone(),
+                },
+            )))
+        })
+        .collect()
+}
+
+#[test]
+fn test_collection_index() {
+    use crate::collections;
+    use crate::settings::CollectionSettings;
+    use std::path::PathBuf;
+
+    let mut settings = Settings::default();
+    settings.collections.insert(
+        "posts".into(),
+        CollectionSettings {
+            pattern: "blog/*.md".into(),
+            title: Some("All posts".into()),
+            index: Some("/archives.html".into()),
+            ..CollectionSettings::default()
+        },
+    );
+    settings
+        .collections
+        .insert("drafts".into(), CollectionSettings::default());
+
+    let docs = vec![Arc::new(DocumentMetadata {
+        url: "blog/post.html".into(),
+        source: PathBuf::from("blog/post.md"),
+        ..DocumentMetadata::default()
+    })];
+
+    let collections = collections::collect(&settings, &docs);
+    let generated = collection_indexes(&settings, &collections, &docs);
+    assert_eq!(generated.len(), 1);
+    assert_eq!(generated[0].metadata.url, "archives.html");
+    assert_eq!(generated[0].metadata.title, "All posts");
+    match generated[0].content {
+        DocumentContent::Index { ref documents } => assert_eq!(documents.len(), 1),
+        _ => panic!("a collection index must list its documents"),
+    }
+}
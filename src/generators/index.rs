@@ -14,22 +14,34 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::super::{Document, DocumentContent, DocumentMetadata, Result};
+use super::super::sections;
+use super::super::{Document, DocumentContent, DocumentMetadata, Result, Settings};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub struct IndexGenerator;
+/// Generates an index of the documents of each directory.
+///
+/// On multilingual websites, a separate index is generated for each language:
+/// `index.fr.html`, or `index.html` inside a language directory such as `fr/`.
+pub struct IndexGenerator {
+    settings: Settings,
+}
 
 impl super::Generator for IndexGenerator {
-    fn new() -> IndexGenerator {
-        IndexGenerator
+    fn new(settings: &Settings) -> IndexGenerator {
+        IndexGenerator {
+            settings: settings.clone(),
+        }
     }
 
-    fn generate(&self, docs: &[Rc<DocumentMetadata>]) -> Result<Vec<Rc<Document>>> {
-        let mut indexes: HashMap<String, Vec<Rc<DocumentMetadata>>> = HashMap::new();
+    fn generate(&self, docs: &[Arc<DocumentMetadata>]) -> Result<Vec<Arc<Document>>> {
+        let mut indexes: HashMap<(String, Option<String>), Vec<Arc<DocumentMetadata>>> =
+            HashMap::new();
 
         for doc in docs.iter() {
+            let language = sections::index_language(doc.language.as_deref(), &self.settings);
+            let language_dir = language.as_deref().map(Path::new);
             let mut path: PathBuf = PathBuf::from(&doc.url);
 
             while let Some(parent) = path.clone().parent() {
@@ -38,26 +50,45 @@ impl super::Generator for IndexGenerator {
                     None => continue,
                 };
 
-                let documents = indexes.entry(index_url).or_insert_with(Vec::new);
+                let documents = indexes.entry((index_url, language.clone())).or_default();
                 documents.push(doc.clone());
 
+                // Documents inside a language directory are not listed above it
+                if Some(parent) == language_dir {
+                    break;
+                }
                 path = parent.into();
             }
         }
 
+        let titles: HashMap<String, String> = docs
+            .iter()
+            .map(|doc| (doc.url.trim_start_matches('/').into(), doc.title.clone()))
+            .collect();
+
         Ok(indexes
             .into_iter()
-            .map(|(url, mut docs)| {
+            .map(|((url, language), mut docs)| {
+                sections::sort(&mut docs, self.settings.section(&url));
+
+                let index_url = sections::index_url(&url, language.as_deref());
+                let language = language.or_else(|| self.settings.site_language.clone());
                 let meta = DocumentMetadata {
-                    url: format!("{}/index.html", url),
-                    title: format!("Index of {}", if url != "" { url } else { "/".into() }),
+                    breadcrumbs: sections::breadcrumbs(
+                        &index_url,
+                        language.as_deref(),
+                        &titles,
+                        &self.settings,
+                    ),
+                    url: index_url,
+                    title: sections::section_title(&url, &self.settings),
+                    language,
                     ..DocumentMetadata::default()
                 };
 
-                docs.sort_by(|b, a| a.created.cmp(&b.created));
                 let content = DocumentContent::Index { documents: docs };
 
-                Rc::new(Document::new(meta, content))
+                Arc::new(Document::new(meta, content))
             })
             .collect())
     }
@@ -15,15 +15,19 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::document::{Document, DocumentMetadata};
-use super::Result;
-use std::rc::Rc;
+use super::{Result, Settings};
+use std::sync::Arc;
 
+mod collection;
 mod index;
+mod search;
+pub use self::collection::collection_indexes;
 pub use self::index::IndexGenerator;
+pub use self::search::SearchGenerator;
 
-pub trait Generator {
-    fn new() -> Self
+pub trait Generator: Send + Sync {
+    fn new(settings: &Settings) -> Self
     where
         Self: Sized;
-    fn generate(&self, docs: &[Rc<DocumentMetadata>]) -> Result<Vec<Rc<Document>>>;
+    fn generate(&self, docs: &[Arc<DocumentMetadata>]) -> Result<Vec<Arc<Document>>>;
 }
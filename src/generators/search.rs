@@ -0,0 +1,218 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::settings::SearchSettings;
+use super::super::{Document, DocumentContent, DocumentMetadata, Error, Result, Settings};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/// Generates a JSON index of the documents, used to search the website
+/// without a server.
+pub struct SearchGenerator {
+    site_url: String,
+    settings: SearchSettings,
+}
+
+impl SearchGenerator {
+    fn is_excluded(&self, url: &str) -> bool {
+        let url = url.trim_start_matches('/');
+        self.settings.exclude.iter().any(|pattern| {
+            let pattern = pattern.trim_matches('/');
+            url == pattern || url.starts_with(&format!("{}/", pattern))
+        })
+    }
+
+    fn entry(&self, doc: &DocumentMetadata) -> Map<String, Value> {
+        let mut entry = Map::new();
+        entry.insert("url".into(), absolute_url(&self.site_url, &doc.url).into());
+
+        for field in self.settings.fields.iter() {
+            let value: Value = match field.as_str() {
+                "title" => doc.title.clone().into(),
+                "keywords" => doc.keywords.clone().into(),
+                "headings" => doc.headings.clone().into(),
+                "content" => truncate(&doc.text, self.settings.content_length).into(),
+                "language" => serde_json::to_value(&doc.language).unwrap_or_default(),
+                "created" => serde_json::to_value(&doc.created).unwrap_or_default(),
+                "modified" => serde_json::to_value(&doc.modified).unwrap_or_default(),
+                name => match doc.extra.get(name) {
+                    Some(value) => value.clone().into(),
+                    None => continue,
+                },
+            };
+            entry.insert(field.clone(), value);
+        }
+
+        entry
+    }
+}
+
+/// Joins the URL of the website and the URL of a document with a single slash.
+fn absolute_url(site_url: &str, url: &str) -> String {
+    format!(
+        "{}/{}",
+        site_url.trim_end_matches('/'),
+        url.trim_start_matches('/')
+    )
+}
+
+/// Returns the first `length` characters of `text`, or all of it when
+/// `length` is `0`.
+fn truncate(text: &str, length: usize) -> String {
+    if length == 0 {
+        text.into()
+    } else {
+        text.chars().take(length).collect()
+    }
+}
+
+/// Splits a text into lowercase terms of at least two characters.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.chars().count() > 1)
+        .map(str::to_lowercase)
+}
+
+impl super::Generator for SearchGenerator {
+    fn new(settings: &Settings) -> SearchGenerator {
+        SearchGenerator {
+            site_url: settings.site_url.clone(),
+            settings: settings.search.clone(),
+        }
+    }
+
+    fn generate(&self, docs: &[Arc<DocumentMetadata>]) -> Result<Vec<Arc<Document>>> {
+        if !self.settings.enabled {
+            return Ok(Vec::new());
+        }
+
+        let docs: Vec<&DocumentMetadata> = docs
+            .iter()
+            .map(Arc::as_ref)
+            .filter(|doc| !self.is_excluded(&doc.url))
+            .collect();
+
+        let mut output = Map::new();
+        output.insert(
+            "documents".into(),
+            docs.iter()
+                .map(|doc| Value::Object(self.entry(doc)))
+                .collect(),
+        );
+
+        if self.settings.inverted_index {
+            let mut index: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+            for (position, doc) in docs.iter().enumerate() {
+                let fields = self
+                    .settings
+                    .fields
+                    .iter()
+                    .map(|field| match field.as_str() {
+                        "title" => doc.title.clone(),
+                        "keywords" => doc.keywords.join(" "),
+                        "headings" => doc.headings.join(" "),
+                        "content" => doc.text.clone(),
+                        name => doc.extra.get(name).cloned().unwrap_or_default(),
+                    });
+                for field in fields {
+                    for term in terms(&field) {
+                        index.entry(term).or_default().insert(position);
+                    }
+                }
+            }
+            output.insert("index".into(), serde_json::to_value(index).unwrap());
+        }
+
+        let raw = serde_json::to_string(&output).map_err(|err| Error::Render {
+            cause: Box::new(err),
+        })?;
+
+        let meta = DocumentMetadata {
+            url: self.settings.output.trim_start_matches('/').into(),
+            title: "Search index".into(),
+            ..DocumentMetadata::default()
+        };
+
+        Ok(vec![Arc::new(Document::new(
+            meta,
+            DocumentContent::Raw { raw },
+        ))])
+    }
+}
+
+#[test]
+fn test_search_index() {
+    use super::Generator;
+
+    let mut settings = Settings::default();
+    settings.search.enabled = true;
+    settings.search.inverted_index = true;
+    settings.search.content_length = 9;
+    settings.search.exclude = vec!["drafts".into()];
+
+    let document = |url: &str, title: &str, text: &str| {
+        Arc::new(DocumentMetadata {
+            url: url.into(),
+            title: title.into(),
+            text: text.into(),
+            headings: vec!["Intro".into()],
+            ..DocumentMetadata::default()
+        })
+    };
+    let docs = vec![
+        document("about.html", "About me", "Hello, world!"),
+        document("blog/post.html", "A post", "Hello again"),
+        document("drafts/secret.html", "Secret", "Hello"),
+    ];
+
+    let generated = SearchGenerator::new(&settings).generate(&docs).unwrap();
+    assert_eq!(generated.len(), 1);
+    assert_eq!(generated[0].metadata.url, "search.json");
+
+    let raw = match generated[0].content {
+        DocumentContent::Raw { ref raw } => raw,
+        _ => panic!("the search index must be written as is"),
+    };
+    let index: Value = serde_json::from_str(raw).unwrap();
+    assert_eq!(index["documents"].as_array().unwrap().len(), 2);
+    assert_eq!(index["documents"][0]["url"], "/about.html");
+    assert_eq!(index["documents"][0]["content"], "Hello, wo");
+    assert_eq!(index["documents"][1]["headings"][0], "Intro");
+    assert_eq!(index["index"]["hello"], serde_json::json!([0, 1]));
+    assert_eq!(index["index"]["world"], serde_json::json!([0]));
+    assert!(index["index"].get("secret").is_none());
+    assert!(index["index"].get("a").is_none());
+
+    settings.search.enabled = false;
+    assert!(SearchGenerator::new(&settings)
+        .generate(&docs)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_absolute_url() {
+    for site_url in &["https://example.com", "https://example.com/"] {
+        for url in &["blog/post.html", "/blog/post.html"] {
+            assert_eq!(
+                absolute_url(site_url, url),
+                "https://example.com/blog/post.html"
+            );
+        }
+    }
+    assert_eq!(absolute_url("/", "about.html"), "/about.html");
+}
@@ -0,0 +1,172 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::document::{Document, Translation};
+use super::Settings;
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+fn is_language(code: &str, settings: &Settings) -> bool {
+    settings.languages.contains_key(code) || settings.site_language.as_deref() == Some(code)
+}
+
+/// Detects the language of a document from its path relative to the source
+/// directory.
+///
+/// The language is read from the first directory of the path (`fr/about.md`)
+/// or from a suffix of the file name (`about.fr.md`). Only the languages
+/// declared in the settings are recognized. Defaults to the language of the
+/// website.
+pub fn document_language(relpath: &Path, settings: &Settings) -> Option<String> {
+    let mut components = relpath.components();
+    let first_dir = match (components.next(), components.next()) {
+        (Some(Component::Normal(dir)), Some(_)) => dir.to_str(),
+        _ => None,
+    };
+
+    let suffix = relpath
+        .file_stem()
+        .map(Path::new)
+        .and_then(Path::extension)
+        .and_then(|ext| ext.to_str());
+
+    first_dir
+        .filter(|code| is_language(code, settings))
+        .or_else(|| suffix.filter(|code| is_language(code, settings)))
+        .map(String::from)
+        .or_else(|| settings.site_language.clone())
+}
+
+/// Computes the key shared by all the translations of a document, by removing
+/// the language from its URL.
+pub fn translation_key(url: &str, language: Option<&str>) -> String {
+    let path = Path::new(url).with_extension("");
+    let path = path.to_str().unwrap_or(url);
+
+    match language {
+        None => path.into(),
+        Some(code) => {
+            let prefix = format!("{}/", code);
+            let suffix = format!(".{}", code);
+            if let Some(rest) = path.strip_prefix(prefix.as_str()) {
+                rest.into()
+            } else if let Some(rest) = path.strip_suffix(suffix.as_str()) {
+                rest.into()
+            } else {
+                path.into()
+            }
+        }
+    }
+}
+
+/// Links together the documents which are translations of each other.
+pub fn link_translations(documents: &mut [Document]) {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+
+    for (index, document) in documents.iter().enumerate() {
+        if let Some(ref language) = document.metadata.language {
+            let key = translation_key(&document.metadata.url, Some(language));
+            groups.entry(key).or_default().push(index);
+        }
+    }
+
+    for indexes in groups.values().filter(|indexes| indexes.len() > 1) {
+        let mut translations: Vec<Translation> = indexes
+            .iter()
+            .map(|&index| Translation::from(&documents[index].metadata))
+            .collect();
+        translations.sort_by(|a, b| a.language.cmp(&b.language));
+
+        for &index in indexes {
+            let metadata = &mut documents[index].metadata;
+            metadata.translations = translations
+                .iter()
+                .filter(|translation| translation.url != metadata.url)
+                .cloned()
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+fn multilingual_settings() -> Settings {
+    let mut settings = Settings {
+        site_language: Some("en".into()),
+        ..Settings::default()
+    };
+    settings.languages.insert("fr".into(), Default::default());
+    settings
+}
+
+#[test]
+fn test_document_language() {
+    let settings = multilingual_settings();
+    let language = |path: &str| document_language(Path::new(path), &settings);
+
+    assert_eq!(language("about.md"), Some("en".into()));
+    assert_eq!(language("about.fr.md"), Some("fr".into()));
+    assert_eq!(language("fr/about.md"), Some("fr".into()));
+    assert_eq!(language("fr.md"), Some("en".into()));
+    assert_eq!(language("about.de.md"), Some("en".into()));
+    assert_eq!(
+        document_language(Path::new("about.fr.md"), &Settings::default()),
+        None
+    );
+}
+
+#[test]
+fn test_translation_key() {
+    assert_eq!(translation_key("about.html", Some("en")), "about");
+    assert_eq!(translation_key("about.fr.html", Some("fr")), "about");
+    assert_eq!(translation_key("fr/about.html", Some("fr")), "about");
+    assert_eq!(translation_key("blog/about.html", None), "blog/about");
+}
+
+#[test]
+fn test_link_translations() {
+    use super::{DocumentContent, DocumentMetadata};
+
+    let document = |url: &str, language: &str| {
+        Document::new(
+            DocumentMetadata {
+                url: url.into(),
+                title: url.into(),
+                language: Some(language.into()),
+                ..DocumentMetadata::default()
+            },
+            DocumentContent::from(String::new()),
+        )
+    };
+
+    let mut documents = vec![
+        document("about.html", "en"),
+        document("about.fr.html", "fr"),
+        document("contact.html", "en"),
+    ];
+    link_translations(&mut documents);
+
+    let urls = |document: &Document| -> Vec<String> {
+        document
+            .metadata
+            .translations
+            .iter()
+            .map(|translation| translation.url.clone())
+            .collect()
+    };
+    assert_eq!(urls(&documents[0]), ["about.fr.html"]);
+    assert_eq!(urls(&documents[1]), ["about.html"]);
+    assert!(urls(&documents[2]).is_empty());
+}
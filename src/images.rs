@@ -0,0 +1,527 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::assets::url_path;
+use super::settings::ImageSettings;
+use handlebars::html_escape;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt::Write;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+
+type BoxedError = Box<dyn error::Error + Send + Sync>;
+
+/// The processed images, indexed by the path of their source.
+pub type Images = BTreeMap<String, ImageInfo>;
+
+/// A resized or converted copy of an image.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageVariant {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub mime: String,
+}
+
+/// A processed image, along with its variants.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub mime: String,
+    pub variants: Vec<ImageVariant>,
+}
+
+/// A variant stored in the cache, named after the suffix and the extension
+/// added to the name of the image.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedVariant {
+    suffix: String,
+    extension: String,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    width: u32,
+    height: u32,
+    variants: Vec<CachedVariant>,
+}
+
+/// The files written for a processed image.
+pub struct Processed {
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+    pub info: ImageInfo,
+}
+
+/// Returns the format of an extension, if the pipeline handles it.
+fn format_of(extension: &str) -> Option<ImageFormat> {
+    match extension.to_lowercase().as_str() {
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "png" => Some(ImageFormat::Png),
+        "webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+/// Checks whether a file is an image processed by the pipeline.
+pub fn is_image(relpath: &Path) -> bool {
+    relpath
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(format_of)
+        .is_some()
+}
+
+/// Hashes the content of an image along with the settings used to process
+/// it.
+fn cache_key(content: &[u8], settings: &ImageSettings) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    hasher.update(format!(
+        "{:?}{:?}{}",
+        settings.widths, settings.formats, settings.quality
+    ));
+    let mut key = String::with_capacity(64);
+    for byte in hasher.finalize().iter() {
+        write!(key, "{:02x}", byte).unwrap();
+    }
+    key
+}
+
+/// Decodes an image, applying its orientation since its metadata are not
+/// written in the variants.
+fn decode(content: &[u8]) -> Result<DynamicImage, BoxedError> {
+    let mut decoder = ImageReader::new(Cursor::new(content))
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>, BoxedError> {
+    let mut output = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut output, quality)
+            .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))?,
+        ImageFormat::WebP => {
+            DynamicImage::ImageRgba8(image.to_rgba8()).write_to(&mut output, format)?
+        }
+        _ => image.write_to(&mut output, format)?,
+    }
+    Ok(output.into_inner())
+}
+
+/// Resizes and converts an image, and stores the variants in the cache.
+fn generate(
+    content: &[u8],
+    extension: &str,
+    settings: &ImageSettings,
+    entry_dir: &Path,
+) -> Result<CacheEntry, BoxedError> {
+    let image = decode(content)?;
+    let (width, height) = (image.width(), image.height());
+
+    let mut extensions = vec![extension.to_string()];
+    for format in settings.formats.iter() {
+        let format = format.trim_start_matches('.').to_lowercase();
+        if format_of(&format).is_none() {
+            return Err(format!("`{}` is not a supported image format", format).into());
+        }
+        if format_of(&format) != format_of(extension) && !extensions.contains(&format) {
+            extensions.push(format);
+        }
+    }
+
+    let mut widths: Vec<u32> = settings
+        .widths
+        .iter()
+        .cloned()
+        .filter(|&w| w < width)
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+    widths.push(width);
+
+    fs::create_dir_all(entry_dir)?;
+    let mut variants = Vec::new();
+    for &variant_width in widths.iter() {
+        let resized = if variant_width == width {
+            image.clone()
+        } else {
+            let variant_height = ((u64::from(height) * u64::from(variant_width)
+                + u64::from(width) / 2)
+                / u64::from(width))
+            .max(1) as u32;
+            image.resize_exact(variant_width, variant_height, FilterType::Lanczos3)
+        };
+
+        for extension in extensions.iter() {
+            let suffix = if variant_width == width {
+                String::new()
+            } else {
+                format!("-{}w", variant_width)
+            };
+            let data = encode(&resized, format_of(extension).unwrap(), settings.quality)?;
+            fs::write(
+                entry_dir.join(format!("variant{}.{}", suffix, extension)),
+                data,
+            )?;
+            variants.push(CachedVariant {
+                suffix,
+                extension: extension.clone(),
+                width: resized.width(),
+                height: resized.height(),
+            });
+        }
+    }
+
+    let entry = CacheEntry {
+        width,
+        height,
+        variants,
+    };
+    // The entry is complete once its description is written
+    fs::write(entry_dir.join("entry.json"), serde_json::to_vec(&entry)?)?;
+    Ok(entry)
+}
+
+fn mime_type(extension: &str) -> String {
+    match format_of(extension) {
+        Some(format) => format.to_mime_type().into(),
+        None => "application/octet-stream".into(),
+    }
+}
+
+/// Resizes and converts an image, or reads its variants from the cache when it
+/// was already processed with the same settings.
+pub fn process(
+    relpath: &Path,
+    content: &[u8],
+    settings: &ImageSettings,
+    cache_dir: &Path,
+) -> Result<Processed, BoxedError> {
+    let extension = relpath
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+    let entry_dir = cache_dir.join(cache_key(content, settings));
+
+    // An invalid entry, such as one left by an interrupted build, is
+    // generated again
+    let entry = match fs::read(entry_dir.join("entry.json")) {
+        Ok(entry) => match serde_json::from_slice(&entry) {
+            Ok(entry) => entry,
+            Err(err) => {
+                warn!(
+                    "Invalid cache entry {}: {}, processing {} again",
+                    entry_dir.display(),
+                    err,
+                    relpath.display()
+                );
+                generate(content, extension, settings, &entry_dir)?
+            }
+        },
+        Err(_) => generate(content, extension, settings, &entry_dir)?,
+    };
+
+    let stem = relpath
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let mut files = Vec::new();
+    let mut variants = Vec::new();
+    for variant in entry.variants.iter() {
+        let dest = if variant.suffix.is_empty() && variant.extension == extension {
+            relpath.to_path_buf()
+        } else {
+            relpath.with_file_name(format!("{}{}.{}", stem, variant.suffix, variant.extension))
+        };
+        let cached = entry_dir.join(format!("variant{}.{}", variant.suffix, variant.extension));
+        files.push((dest.clone(), fs::read(cached)?));
+        variants.push(ImageVariant {
+            url: url_path(&dest),
+            width: variant.width,
+            height: variant.height,
+            mime: mime_type(&variant.extension),
+        });
+    }
+
+    Ok(Processed {
+        files,
+        info: ImageInfo {
+            url: url_path(relpath),
+            width: entry.width,
+            height: entry.height,
+            mime: mime_type(extension),
+            variants,
+        },
+    })
+}
+
+/// Returns the `srcset` attribute listing the variants of a type.
+fn srcset(info: &ImageInfo, mime: &str, site_url: &str) -> String {
+    info.variants
+        .iter()
+        .filter(|variant| variant.mime == mime)
+        .map(|variant| format!("{}{} {}w", site_url, variant.url, variant.width))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Returns the HTML of a responsive image. The variants in other formats are
+/// listed in the `<source>` elements of a `<picture>`.
+pub fn markup(
+    info: &ImageInfo,
+    attributes: &[(String, String)],
+    sizes: &str,
+    site_url: &str,
+) -> String {
+    let mut img = format!(
+        "<img src=\"{}{}\" srcset=\"{}\" sizes=\"{}\" width=\"{}\" height=\"{}\"",
+        site_url,
+        html_escape(&info.url),
+        html_escape(&srcset(info, &info.mime, site_url)),
+        html_escape(sizes),
+        info.width,
+        info.height
+    );
+    for (name, value) in attributes.iter() {
+        write!(img, " {}=\"{}\"", name, html_escape(value)).unwrap();
+    }
+    img.push_str(" />");
+
+    let mut mimes: Vec<&str> = Vec::new();
+    for variant in info.variants.iter() {
+        if variant.mime != info.mime && !mimes.contains(&variant.mime.as_str()) {
+            mimes.push(&variant.mime);
+        }
+    }
+    if mimes.is_empty() {
+        return img;
+    }
+
+    let mut picture = String::from("<picture>");
+    for mime in mimes {
+        write!(
+            picture,
+            "<source type=\"{}\" srcset=\"{}\" sizes=\"{}\" />",
+            mime,
+            html_escape(&srcset(info, mime, site_url)),
+            html_escape(sizes)
+        )
+        .unwrap();
+    }
+    picture.push_str(&img);
+    picture.push_str("</picture>");
+    picture
+}
+
+/// Returns the path of the source of an image, relative to the source
+/// directory, from its URL in the document at `document_url`.
+fn resolve(src: &str, document_url: &str, site_url: &str) -> Option<String> {
+    if src.contains("://") || src.starts_with("//") || src.starts_with("data:") {
+        return None;
+    }
+
+    let path = if !site_url.is_empty() && src.starts_with(site_url) {
+        PathBuf::from(&src[site_url.len()..])
+    } else if src.starts_with('/') {
+        PathBuf::from(src.trim_start_matches('/'))
+    } else {
+        Path::new(document_url)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(src)
+    };
+
+    let mut components: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().into()),
+            Component::ParentDir => {
+                components.pop()?;
+            }
+            _ => (),
+        }
+    }
+    Some(components.join("/"))
+}
+
+/// Rewrites the `<img>` tags of a document which display processed images.
+pub struct Rewriter<'a> {
+    tag: Regex,
+    attribute: Regex,
+    images: &'a Images,
+    sizes: &'a str,
+    site_url: &'a str,
+}
+
+impl<'a> Rewriter<'a> {
+    pub fn new(images: &'a Images, settings: &'a ImageSettings, site_url: &'a str) -> Rewriter<'a> {
+        Rewriter {
+            tag: Regex::new(r#"<img\s([^>]*?)\s*/?>"#).unwrap(),
+            attribute: Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap(),
+            images,
+            sizes: &settings.sizes,
+            site_url,
+        }
+    }
+
+    pub fn rewrite(&self, html: &str, document_url: &str) -> String {
+        self.tag
+            .replace_all(html, |captures: &Captures| {
+                let mut src = None;
+                let mut attributes = Vec::new();
+                for attribute in self.attribute.captures_iter(&captures[1]) {
+                    let value = attribute[2]
+                        .replace("&amp;", "&")
+                        .replace("&quot;", "\"")
+                        .replace("&lt;", "<")
+                        .replace("&gt;", ">");
+                    match &attribute[1] {
+                        "src" => src = Some(value),
+                        "srcset" | "width" | "height" => return captures[0].to_string(),
+                        name => attributes.push((name.to_string(), value)),
+                    }
+                }
+
+                let info = src
+                    .and_then(|src| resolve(&src, document_url, self.site_url))
+                    .and_then(|path| self.images.get(&path));
+                match info {
+                    Some(info) => markup(info, &attributes, self.sizes, self.site_url),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+#[test]
+fn test_resolve() {
+    assert_eq!(
+        resolve("photo.jpg", "blog/post.html", "/"),
+        Some("blog/photo.jpg".into())
+    );
+    assert_eq!(
+        resolve("../img/photo.jpg", "blog/post.html", "/"),
+        Some("img/photo.jpg".into())
+    );
+    assert_eq!(
+        resolve("/site/img/photo.jpg", "blog/post.html", "/site/"),
+        Some("img/photo.jpg".into())
+    );
+    assert_eq!(resolve("../../photo.jpg", "blog/post.html", "/"), None);
+    assert_eq!(resolve("https://example.com/a.jpg", "post.html", "/"), None);
+}
+
+#[test]
+fn test_rewrite() {
+    let variant = |url: &str, width: u32, mime: &str| ImageVariant {
+        url: url.into(),
+        width,
+        height: width / 2,
+        mime: mime.into(),
+    };
+    let mut images = Images::new();
+    images.insert(
+        "img/photo.jpg".into(),
+        ImageInfo {
+            url: "img/photo.jpg".into(),
+            width: 800,
+            height: 400,
+            mime: "image/jpeg".into(),
+            variants: vec![
+                variant("img/photo-480w.jpg", 480, "image/jpeg"),
+                variant("img/photo-480w.webp", 480, "image/webp"),
+                variant("img/photo.jpg", 800, "image/jpeg"),
+                variant("img/photo.webp", 800, "image/webp"),
+            ],
+        },
+    );
+    let settings = ImageSettings::default();
+    let rewriter = Rewriter::new(&images, &settings, "/");
+
+    assert_eq!(
+        rewriter.rewrite("<p><img src=\"../img/photo.jpg\" alt=\"A &quot;photo&quot;\" /></p>", "blog/post.html"),
+        "<p><picture>\
+         <source type=\"image/webp\" srcset=\"/img/photo-480w.webp 480w, /img/photo.webp 800w\" sizes=\"100vw\" />\
+         <img src=\"/img/photo.jpg\" srcset=\"/img/photo-480w.jpg 480w, /img/photo.jpg 800w\" sizes=\"100vw\" \
+         width=\"800\" height=\"400\" alt=\"A &quot;photo&quot;\" /></picture></p>"
+    );
+
+    let unchanged =
+        "<img src=\"img/other.png\" alt=\"\" /><img src=\"img/photo.jpg\" width=\"10\">";
+    assert_eq!(rewriter.rewrite(unchanged, "index.html"), unchanged);
+}
+
+#[test]
+fn test_process() {
+    let dir = crate::utils::TempDir::new("images");
+    let mut content = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image::RgbImage::from_fn(100, 50, |x, y| {
+        image::Rgb([x as u8, y as u8, 0])
+    }))
+    .write_to(&mut content, ImageFormat::Png)
+    .unwrap();
+    let content = content.into_inner();
+
+    let settings = ImageSettings {
+        widths: vec![40, 200],
+        ..ImageSettings::default()
+    };
+    let processed = process(Path::new("img/a.png"), &content, &settings, &dir).unwrap();
+    // The second time, the variants are read from the cache
+    let cached = process(Path::new("img/b.png"), &content, &settings, &dir).unwrap();
+
+    // A truncated entry is replaced
+    let entry_path = dir.join(cache_key(&content, &settings)).join("entry.json");
+    let entry = fs::read(&entry_path).unwrap();
+    fs::write(&entry_path, &entry[..entry.len() / 2]).unwrap();
+    let repaired = process(Path::new("img/a.png"), &content, &settings, &dir).unwrap();
+    assert_eq!(repaired.files, processed.files);
+    assert_eq!(fs::read(&entry_path).unwrap(), entry);
+
+    let names: Vec<String> = processed
+        .files
+        .iter()
+        .map(|(path, _)| url_path(path))
+        .collect();
+    assert_eq!(
+        names,
+        ["img/a-40w.png", "img/a-40w.webp", "img/a.png", "img/a.webp"]
+    );
+    assert_eq!((processed.info.width, processed.info.height), (100, 50));
+    assert_eq!(processed.info.variants[0].height, 20);
+    assert_eq!(processed.info.variants[1].mime, "image/webp");
+
+    assert_eq!(cached.info.url, "img/b.png");
+    assert_eq!(cached.info.variants[3].url, "img/b.webp");
+    assert_eq!(cached.files[3].1, processed.files[3].1);
+}
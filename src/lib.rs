@@ -15,50 +15,120 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 extern crate chrono;
+extern crate csv;
+extern crate globset;
+extern crate grass;
 extern crate handlebars;
+extern crate ignore;
+extern crate image;
+extern crate lightningcss;
+extern crate minify_html;
+extern crate minify_js;
 extern crate num;
 extern crate pulldown_cmark;
+extern crate rayon;
 extern crate regex;
 extern crate serde;
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate sha2;
 extern crate toml;
 extern crate walkdir;
 #[macro_use]
 extern crate log;
 
+mod assets;
+mod cleanup;
+mod collections;
+mod data;
+mod dates;
 mod document;
 mod error;
 mod generators;
+mod i18n;
+mod images;
+mod menus;
 pub mod metadata;
+mod minify;
+pub mod processors;
 pub mod readers;
+mod related;
+mod sass;
+mod sections;
 mod settings;
 mod site;
+mod sources;
 mod templates;
 mod utils;
 
 use handlebars::Handlebars;
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use walkdir::{DirEntry, WalkDir};
 
-pub use crate::document::{Document, DocumentContent, DocumentMetadata};
+use crate::collections::Collections;
+pub use crate::document::{
+    Breadcrumb, Document, DocumentContent, DocumentLink, DocumentMetadata, Summary, Translation,
+};
 pub use crate::error::{Error, Result};
 pub use crate::generators::Generator;
+pub use crate::menus::MenuEntry;
+pub use crate::processors::Processor;
 use crate::readers::Reader;
-pub use crate::settings::Settings;
-pub use crate::site::Site;
-use crate::templates::Context;
+pub use crate::settings::{
+    AssetSettings, CollectionSettings, CommandInput, CommandReaderSettings, DatesSource,
+    ImageSettings, LanguageSettings, MenuEntrySettings, MinifySettings, RelatedSettings,
+    SassSettings, SassStyle, SearchSettings, SectionSettings, Settings, SortKey, SortOrder,
+};
+pub use crate::site::{Site, SiteLanguage};
+use crate::templates::{Catalog, Contents, Context, PageContext};
+use globset::GlobMatcher;
+
+/// The dependency graph of the processed files, relative to the cache
+/// directory.
+const DEPENDENCIES_FILE: &str = "dependencies.json";
+
+/// The files written when copying a static file.
+#[derive(Default)]
+struct CopiedFile {
+    /// The URL paths of the fingerprinted assets, with their original paths
+    assets: Vec<(String, String)>,
+    /// The variants of a processed image
+    image: Option<(String, images::ImageInfo)>,
+    /// The inputs and the outputs of a processed file
+    node: Option<(String, processors::Node)>,
+}
+
+impl From<Option<(String, String)>> for CopiedFile {
+    fn from(asset: Option<(String, String)>) -> CopiedFile {
+        CopiedFile {
+            assets: asset.into_iter().collect(),
+            ..CopiedFile::default()
+        }
+    }
+}
 
 pub struct Compiler {
     pub settings: Settings,
     pub site: Site,
     handlebars: Handlebars<'static>,
-    readers: HashMap<String, Rc<dyn Reader>>,
-    generators: Vec<Rc<dyn Generator>>,
-    documents: HashMap<String, Rc<DocumentMetadata>>,
+    readers: HashMap<String, Arc<dyn Reader>>,
+    generators: Vec<Arc<dyn Generator>>,
+    processors: HashMap<String, Arc<dyn Processor>>,
+    /// The processors registered for glob patterns, by order of registration
+    processor_patterns: Vec<(GlobMatcher, Arc<dyn Processor>)>,
+    documents: BTreeMap<String, Arc<DocumentMetadata>>,
+    /// The files written by the build, relative to the output directory
+    written: Mutex<BTreeSet<String>>,
+    /// Whether a source file could not be built, in which case its previous
+    /// outputs must not be removed as stale files
+    failed: AtomicBool,
 }
 
 impl Compiler {
@@ -68,11 +138,22 @@ impl Compiler {
             readers: HashMap::new(),
             handlebars: Handlebars::new(),
             site: Site::new(settings),
-            documents: HashMap::new(),
+            documents: BTreeMap::new(),
             generators: Vec::new(),
+            processors: HashMap::new(),
+            processor_patterns: Vec::new(),
+            written: Mutex::new(BTreeSet::new()),
+            failed: AtomicBool::new(false),
         };
         compiler.add_reader::<readers::MarkdownReader>();
+        compiler.add_reader::<readers::HtmlReader>();
+        compiler.add_reader::<readers::RstReader>();
+        compiler.add_reader::<readers::OrgReader>();
+        compiler.add_reader::<readers::NotebookReader>();
+        compiler.add_command_readers();
         compiler.add_generator::<generators::IndexGenerator>();
+        compiler.add_generator::<generators::SearchGenerator>();
+        compiler.add_processor::<processors::SassProcessor>();
         compiler
     }
 
@@ -98,23 +179,68 @@ impl Compiler {
         Ok(())
     }
 
-    pub fn get_reader(&self, path: &Path) -> Option<Rc<dyn Reader>> {
+    pub fn get_reader(&self, path: &Path) -> Option<Arc<dyn Reader>> {
         path.extension()
             .and_then(|extension| extension.to_str())
             .and_then(|extension_str| self.readers.get(extension_str))
+            .filter(|reader| reader.accepts(path))
             .cloned()
     }
 
     pub fn add_reader<T: Reader + 'static>(&mut self) {
-        let reader = Rc::new(T::new(&self.settings));
+        let reader = Arc::new(T::new(&self.settings));
 
         for &extension in T::extensions() {
             self.readers.insert(extension.into(), reader.clone());
         }
     }
 
+    /// Registers the external commands declared in the settings, which
+    /// replace the builtin readers of the same extensions.
+    fn add_command_readers(&mut self) {
+        let reader = readers::CommandReader::new(&self.settings);
+        let extensions = reader.configured_extensions();
+        let reader: Arc<dyn Reader> = Arc::new(reader);
+
+        for extension in extensions {
+            self.readers.insert(extension, reader.clone());
+        }
+    }
+
     pub fn add_generator<T: Generator + 'static>(&mut self) {
-        self.generators.push(Rc::new(T::new()));
+        self.generators.push(Arc::new(T::new(&self.settings)));
+    }
+
+    /// Returns the processor of a file, the patterns registered last taking
+    /// precedence over the previous ones and over the extensions.
+    pub fn get_processor(&self, relpath: &Path) -> Option<Arc<dyn Processor>> {
+        self.processor_patterns
+            .iter()
+            .rev()
+            .find(|(matcher, _)| matcher.is_match(relpath))
+            .map(|(_, processor)| processor)
+            .or_else(|| {
+                relpath
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(|extension| self.processors.get(extension))
+            })
+            .cloned()
+    }
+
+    pub fn add_processor<T: Processor + 'static>(&mut self) {
+        let processor: Arc<dyn Processor> = Arc::new(T::new(&self.settings));
+
+        for pattern in processor.patterns() {
+            if processors::is_extension(&pattern) {
+                self.processors.insert(pattern, processor.clone());
+                continue;
+            }
+            match collections::compile_pattern(pattern.trim_start_matches('/')) {
+                Ok(matcher) => self.processor_patterns.push((matcher, processor.clone())),
+                Err(err) => error!("Invalid pattern \"{}\": {}", pattern, err),
+            }
+        }
     }
 
     fn load_templates(&mut self) -> Result<()> {
@@ -133,69 +259,138 @@ impl Compiler {
         Ok(())
     }
 
-    fn render_context(&self, context: Context, path: &Path) -> Result<()> {
-        let output: String = self.handlebars.render("page.html", &context)
-                .map_err(|err| Error::Render {
-                    cause: Box::new(err)
-                })?;
+    fn render_context(
+        &self,
+        catalog: &Catalog,
+        contents: &Contents,
+        page_context: &mut PageContext,
+        document: &Document,
+        path: &Path,
+    ) -> Result<()> {
+        let site = self.site.localized(document.metadata.language.as_deref());
+        let contents = Some(contents).filter(|_| self.shows_full_content(document));
+        let output: String = page_context
+            .render(
+                &self.handlebars,
+                "page.html",
+                &Context::new(&site, catalog, document, contents),
+            )
+            .map_err(|err| Error::Render {
+                cause: Box::new(err),
+            })?;
+
+        if self.settings.minify.html {
+            self.write_output(path, minify::minify_html(&output, &self.settings.minify))
+        } else {
+            self.write_output(path, &output)
+        }
+    }
 
-        let dest_file = self.settings.output_dir.join(&path);
+    /// Tells whether a document is an index listing the content of its
+    /// documents, as set by `[sections.<directory>] full_content`.
+    fn shows_full_content(&self, document: &Document) -> bool {
+        let section = sections::section_of(&document.metadata.url);
+        match document.content {
+            DocumentContent::Index { .. } => self.settings.section(section).full_content,
+            _ => false,
+        }
+    }
+
+    fn write_output<C: AsRef<[u8]>>(&self, path: &Path, output: C) -> Result<()> {
+        let dest_file = self.settings.output_dir.join(path);
         let dest_dir = dest_file.parent().unwrap();
-        fs::create_dir_all(&dest_dir)
+        fs::create_dir_all(dest_dir)
             .and_then(|_| {
                 let mut fd = File::create(&dest_file)?;
-                fd.write(output.as_ref())?;
+                fd.write_all(output.as_ref())?;
                 fd.sync_data()?;
                 Ok(())
             })
             .map_err(|err| Error::Output {
                 dest: dest_dir.into(),
                 cause: Box::new(err),
-            })
+            })?;
+        self.mark_written(path);
+        Ok(())
     }
 
-    fn build_document(&mut self, reader: Rc<dyn Reader>, path: &Path) -> Result<()> {
-        let (body, metadata) = reader.load(path)?;
-        let dest = path
-            .strip_prefix(&self.settings.source_dir)
-            .map(|relpath| relpath.with_extension("html"))
-            .unwrap();
+    /// Reports a source file which could not be built.
+    fn report_failure(&self, err: Error) {
+        error!("{}", err);
+        self.failed.store(true, Ordering::Relaxed);
+    }
 
-        let document = Document {
-            metadata: DocumentMetadata {
-                url: dest.to_str().unwrap().into(),
-                .. DocumentMetadata::from_raw(metadata.into_iter())?
-            },
-            content: DocumentContent::from(body),
+    /// Records a file written to the output directory, which is then not
+    /// removed as a stale file.
+    fn mark_written(&self, relpath: &Path) {
+        self.written
+            .lock()
+            .unwrap()
+            .insert(assets::url_path(relpath));
+    }
+
+    fn load_document(&self, reader: Arc<dyn Reader>, path: &Path) -> Result<Document> {
+        let (content, metadata) = reader.load(path)?;
+        let relpath = path.strip_prefix(&self.settings.source_dir).unwrap();
+        let dest = relpath.with_extension("html");
+
+        let word_count = content.text.split_whitespace().count();
+        let mut metadata = DocumentMetadata {
+            url: dest.to_str().unwrap().into(),
+            source: relpath.into(),
+            word_count,
+            reading_time: document::reading_time(word_count, self.settings.words_per_minute),
+            ..DocumentMetadata::from_raw(metadata.into_iter(), &self.settings)?
         };
 
-        debug!(
-            "Rendering document {} in {} ...",
-            path.display(),
-            dest.display()
-        );
-        self.render_context(Context::new(&self.site, &document), &dest)
-            .and_then(|_| {
-                self.documents.insert(
-                    dest.to_str().unwrap().into(),
-                    Rc::new(document.metadata.clone()),
-                );
-                Ok(())
-            })
+        // The summary marked in the body takes precedence over the metadata
+        if content.summary.is_some() {
+            metadata.summary = content.summary;
+        } else if metadata.summary.is_none() {
+            metadata.summary = Some(Summary::from_text(
+                &content.text,
+                self.settings.summary_words,
+            ));
+        }
+        metadata.text = content.text;
+        metadata.headings = content.headings;
+        dates::fill_missing_dates(&mut metadata, path, &self.settings);
+        if metadata.language.is_none() {
+            metadata.language = i18n::document_language(relpath, &self.settings);
+        }
+
+        Ok(Document {
+            metadata,
+            content: DocumentContent::from(content.html),
+        })
     }
 
-    fn copy_file(&mut self, path: &Path) -> Result<()> {
-        let dest = path
-            .strip_prefix(&self.settings.source_dir)
-            .map(|relpath| self.settings.output_dir.join(relpath))
-            .unwrap();
+    fn build_document(
+        &self,
+        catalog: &Catalog,
+        contents: &Contents,
+        page_context: &mut PageContext,
+        document: &Document,
+    ) -> Result<()> {
+        let dest = utils::remove_path_prefix(&document.metadata.url);
+
+        debug!("Rendering document {} ...", dest.display());
+        match document.content {
+            DocumentContent::Raw { ref raw } => self.write_output(&dest, raw),
+            _ => self.render_context(catalog, contents, page_context, document, &dest),
+        }
+    }
+
+    fn copy_file(&self, path: &Path) -> Result<()> {
+        let relpath = path.strip_prefix(&self.settings.source_dir).unwrap();
+        let dest = self.settings.output_dir.join(relpath);
         let dest_dir = dest.parent().unwrap();
 
-        fs::create_dir_all(&dest_dir)
+        fs::create_dir_all(dest_dir)
             .and_then(|_| fs::copy(path, &dest))
-            .and_then(|_| {
+            .map(|_| {
                 debug!("Copying {} to {}", path.display(), dest.display());
-                Ok(())
+                self.mark_written(relpath);
             })
             .map_err(|err| Error::Copy {
                 from: path.into(),
@@ -204,40 +399,274 @@ impl Compiler {
             })
     }
 
-    fn run_generators(&mut self) -> Result<()> {
-        let documents: Vec<Rc<DocumentMetadata>> = self.documents.values().cloned().collect();
+    /// Copies a static file, or writes it processed, minified, resized or
+    /// under its fingerprinted name when it is an asset.
+    fn copy_static_file(&self, path: &Path, graph: &processors::Graph) -> Result<CopiedFile> {
+        let relpath = path.strip_prefix(&self.settings.source_dir).unwrap();
+        if let Some(processor) = self.get_processor(relpath) {
+            return self.process_file(processor, path, relpath, graph);
+        }
+        if self.settings.images.enabled && images::is_image(relpath) {
+            match self.process_image(path, relpath) {
+                Ok(copied) => return Ok(copied),
+                Err(err) => warn!("{}, copying it unchanged", err),
+            }
+        }
 
-        for generator in self.generators.iter() {
-            let generated_docs = generator.generate(documents.as_ref())?;
+        let minified = minify::is_minified(&self.settings.minify, relpath);
+        if !minified && !self.is_fingerprinted(relpath) {
+            return self.copy_file(path).map(|_| CopiedFile::default());
+        }
 
-            for generated_doc in generated_docs.iter() {
-                if self.documents.contains_key(&generated_doc.metadata.url) {
-                    continue;
-                }
-                trace!("Running generator");
+        let content = self.read_static_file(path)?;
+        let content = if minified {
+            minify::minify_file(relpath, content)
+        } else {
+            content
+        };
+        debug!("Copying {} ...", path.display());
+        self.write_asset(relpath, &content).map(CopiedFile::from)
+    }
 
-                let dest = utils::remove_path_prefix(&generated_doc.metadata.url);
-                if let Err(e) = self.render_context(Context::new(&self.site, generated_doc), &dest)
-                {
-                    return Err(e);
+    /// Writes the outputs of a processor, unless the file and its
+    /// dependencies did not change since the previous build.
+    fn process_file(
+        &self,
+        processor: Arc<dyn Processor>,
+        path: &Path,
+        relpath: &Path,
+        graph: &processors::Graph,
+    ) -> Result<CopiedFile> {
+        let Settings {
+            ref source_dir,
+            ref output_dir,
+            ..
+        } = self.settings;
+        let source = assets::url_path(relpath);
+        if let Some(node) = graph.fresh(&source, source_dir, output_dir) {
+            trace!("{} is up to date", path.display());
+            for output in node.outputs.iter() {
+                self.mark_written(Path::new(output));
+            }
+            return Ok(CopiedFile {
+                assets: node.assets.clone(),
+                node: Some((source, node.clone())),
+                ..CopiedFile::default()
+            });
+        }
+
+        debug!("Processing {} ...", path.display());
+        let processed = processor.process(path, relpath)?;
+        let mut copied = CopiedFile::default();
+        let mut outputs = Vec::new();
+        for output in processed.outputs {
+            let content = if minify::is_minified(&self.settings.minify, &output.path) {
+                minify::minify_file(&output.path, output.content)
+            } else {
+                output.content
+            };
+            match self.write_asset(&output.path, &content)? {
+                Some((original, fingerprinted)) => {
+                    outputs.push(original.clone());
+                    outputs.push(fingerprinted.clone());
+                    copied.assets.push((original, fingerprinted));
                 }
+                None => outputs.push(assets::url_path(&output.path)),
             }
         }
 
-        Ok(())
+        let mut inputs = processed.dependencies;
+        inputs.insert(0, path.into());
+        let mut node = processors::Node::new(source_dir, &inputs, outputs);
+        node.assets = copied.assets.clone();
+        copied.node = Some((source, node));
+        Ok(copied)
+    }
+
+    /// Writes the resized and converted variants of an image.
+    fn process_image(&self, path: &Path, relpath: &Path) -> Result<CopiedFile> {
+        let error = |cause| Error::Image {
+            path: path.into(),
+            cause,
+        };
+        let content = fs::read(path).map_err(|err| error(Box::new(err)))?;
+        let cache_dir = self.settings.cache_dir().join("images");
+        let processed =
+            images::process(relpath, &content, &self.settings.images, &cache_dir).map_err(error)?;
+        debug!("Resizing {} ...", path.display());
+
+        let mut copied = CopiedFile::default();
+        for (dest, content) in processed.files.iter() {
+            copied.assets.extend(self.write_asset(dest, content)?);
+        }
+
+        // The variants are linked under their fingerprinted names
+        let mut info = processed.info;
+        let source = info.url.clone();
+        let fingerprinted = |url: &mut String| {
+            if let Some((_, dest)) = copied.assets.iter().find(|(path, _)| path == url) {
+                *url = dest.clone();
+            }
+        };
+        fingerprinted(&mut info.url);
+        for variant in info.variants.iter_mut() {
+            fingerprinted(&mut variant.url);
+        }
+        copied.image = Some((source, info));
+        Ok(copied)
+    }
+
+    /// Reads a static file, or the outputs of its processor if it has one.
+    fn read_static_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let relpath = path.strip_prefix(&self.settings.source_dir).unwrap_or(path);
+        if let Some(processor) = self.get_processor(relpath) {
+            return processor.process(path, relpath).map(|processed| {
+                processed
+                    .outputs
+                    .into_iter()
+                    .flat_map(|output| output.content)
+                    .collect()
+            });
+        }
+
+        fs::read(path).map_err(|err| Error::Copy {
+            from: path.into(),
+            to: self.settings.output_dir.clone(),
+            cause: Box::new(err),
+        })
+    }
+
+    fn is_fingerprinted(&self, relpath: &Path) -> bool {
+        self.settings.assets.fingerprint && assets::is_asset(&self.settings.assets, relpath)
+    }
+
+    /// Writes an asset, and a copy under its fingerprinted name if needed.
+    ///
+    /// The original path is kept for the URLs which do not go through the
+    /// `asset` helper, such as `url()` in stylesheets or images in Markdown.
+    fn write_asset(&self, relpath: &Path, content: &[u8]) -> Result<Option<(String, String)>> {
+        self.write_output(relpath, content)?;
+        if !self.is_fingerprinted(relpath) {
+            return Ok(None);
+        }
+
+        let dest = assets::fingerprint(relpath, content, self.settings.assets.hash_length);
+        self.write_output(&dest, content)?;
+        Ok(Some((assets::url_path(relpath), assets::url_path(&dest))))
+    }
+
+    /// Concatenates the files of a bundle, then minifies and writes it.
+    fn build_bundle(&self, bundle: &str, files: &[String]) -> Result<Option<(String, String)>> {
+        let mut content = Vec::new();
+        for file in files.iter() {
+            let path = self.settings.source_dir.join(file.trim_start_matches('/'));
+            content.extend(self.read_static_file(&path)?);
+            if !content.ends_with(b"\n") {
+                content.push(b'\n');
+            }
+        }
+
+        let relpath = Path::new(bundle);
+        if minify::is_minified(&self.settings.minify, relpath) {
+            content = minify::minify_file(relpath, content);
+        }
+        debug!("Writing the bundle {}", bundle);
+        self.write_asset(relpath, &content)
+    }
+
+    fn run_generators(&self, collections: &Collections) -> Result<Vec<Arc<Document>>> {
+        let documents: Vec<Arc<DocumentMetadata>> = self.documents.values().cloned().collect();
+        let mut generated = Vec::new();
+
+        for generator in self.generators.iter() {
+            trace!("Running generator");
+            generated.extend(generator.generate(documents.as_ref())?);
+        }
+        generated.extend(generators::collection_indexes(
+            &self.settings,
+            collections,
+            &documents,
+        ));
+
+        generated.retain(|generated_doc| {
+            let dest = utils::remove_path_prefix(&generated_doc.metadata.url);
+            !self
+                .documents
+                .contains_key(dest.to_str().unwrap_or_default())
+        });
+        Ok(generated)
     }
 
     pub fn run(&mut self) -> Result<()> {
         self.check_settings()?;
         self.load_templates()?;
 
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.settings.jobs)
+            .build()
+            .map_err(|err| Error::Settings {
+                message: format!("Could not start the worker threads: {}", err),
+            })?;
+
+        if self.settings.clean {
+            info!("Cleaning {} ...", self.settings.output_dir.display());
+            cleanup::clean(&self.settings)?;
+        }
+        self.written.get_mut().unwrap().clear();
+        *self.failed.get_mut() = false;
+        pool.install(|| self.build())?;
+        self.remove_stale_files()
+    }
+
+    /// Removes the files written by the previous build which were not
+    /// written again, and saves the list of the files of this build.
+    fn remove_stale_files(&self) -> Result<()> {
+        let manifest_path = self.settings.cache_dir().join(cleanup::OUTPUT_MANIFEST);
+        let output_dir = &self.settings.output_dir;
+        let previous = cleanup::OutputManifest::load(&manifest_path, output_dir);
+        let mut current = cleanup::OutputManifest {
+            output_dir: output_dir.clone(),
+            files: self.written.lock().unwrap().clone(),
+        };
+
+        // The outputs of the files which failed are unknown, so they are all
+        // kept until the next successful build
+        if self.failed.load(Ordering::Relaxed) {
+            warn!("Some files could not be built, the stale files are not removed");
+            current.files.extend(previous.files);
+        } else {
+            let keep = cleanup::keep_matcher(&self.settings)?;
+            let removed = cleanup::remove_stale_files(output_dir, &previous, &current, &keep);
+            if removed > 0 {
+                info!(
+                    "Removed {} stale files from {}",
+                    removed,
+                    output_dir.display()
+                );
+            }
+        }
+
+        current.save(&manifest_path).map_err(|err| Error::Output {
+            dest: manifest_path.clone(),
+            cause: Box::new(err),
+        })
+    }
+
+    fn build(&mut self) -> Result<()> {
+        let data = data::load_data(&self.settings.data_dir)?;
+        let filter = sources::SourceFilter::new(&self.settings)?;
+
         let entries = WalkDir::new(&self.settings.source_dir)
             .min_depth(1)
             .max_depth(self.settings.max_depth)
             .follow_links(self.settings.follow_links)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
             .into_iter();
 
-        for entry in entries.filter_entry(filter_entry) {
+        let mut sources = Vec::new();
+        let mut static_files = Vec::new();
+
+        for entry in entries.filter_entry(|entry| filter.accepts_entry(entry)) {
             let entry = match entry {
                 Err(_) => continue,
                 Ok(e) => {
@@ -249,28 +678,281 @@ impl Compiler {
                 }
             };
 
-            let result = match self.get_reader(&entry) {
-                Some(reader) => self.build_document(reader.clone(), &entry),
-                None => self.copy_file(&entry),
-            };
+            match self.get_reader(&entry) {
+                Some(reader) => sources.push((reader, entry)),
+                None => static_files.push(entry),
+            }
+        }
+
+        let graph_path = self.settings.cache_dir().join(DEPENDENCIES_FILE);
+        // The number of threads and the cleaning do not change the outputs
+        let settings = Settings {
+            jobs: 0,
+            clean: false,
+            ..self.settings.clone()
+        };
+        let settings_hash = assets::content_hash(format!("{:?}", settings).as_bytes());
+        let graph = processors::Graph::load(&graph_path, settings_hash.clone());
+
+        let this = &*self;
+        type Copied = Result<CopiedFile>;
+        let (loaded, copied): (Vec<Result<Document>>, Vec<Copied>) = rayon::join(
+            || {
+                sources
+                    .into_par_iter()
+                    .map(|(reader, path)| this.load_document(reader, &path))
+                    .collect()
+            },
+            || {
+                static_files
+                    .par_iter()
+                    .map(|path| this.copy_static_file(path, &graph))
+                    .collect()
+            },
+        );
 
-            if let Err(err) = result {
-                error!("{}", err);
+        let bundles: Vec<Copied> = self
+            .settings
+            .assets
+            .bundles
+            .par_iter()
+            .map(|(bundle, files)| this.build_bundle(bundle, files).map(CopiedFile::from))
+            .collect();
+
+        let mut manifest = assets::Manifest::new();
+        let mut processed_images = images::Images::new();
+        let mut graph = processors::Graph::new(settings_hash);
+        for result in copied.into_iter().chain(bundles) {
+            match result {
+                Ok(copied) => {
+                    processed_images.extend(copied.image);
+                    if let Some((source, node)) = copied.node {
+                        graph.insert(source, node);
+                    }
+                    manifest.extend(copied.assets);
+                }
+                Err(err) => self.report_failure(err),
             }
         }
+        let saved = if graph.is_empty() && !graph_path.exists() {
+            Ok(())
+        } else {
+            graph.save(&graph_path)
+        };
+        if let Err(err) = saved {
+            warn!(
+                "Could not write the dependency graph {}: {}",
+                graph_path.display(),
+                err
+            );
+        }
+        if self.settings.assets.fingerprint && !self.settings.assets.manifest.is_empty() {
+            let json = serde_json::to_string_pretty(&manifest).map_err(|err| Error::Render {
+                cause: Box::new(err),
+            })?;
+            self.write_output(Path::new(&self.settings.assets.manifest), json)?;
+        }
 
-        self.run_generators()?;
+        let mut documents: Vec<Document> = loaded
+            .into_iter()
+            .filter_map(|result| result.map_err(|err| self.report_failure(err)).ok())
+            .collect();
+
+        if !processed_images.is_empty() {
+            let rewriter = images::Rewriter::new(
+                &processed_images,
+                &self.settings.images,
+                &self.settings.site_url,
+            );
+            for document in documents.iter_mut() {
+                let url = document.metadata.url.clone();
+                if let DocumentContent::Text { ref mut content } = document.content {
+                    *content = rewriter.rewrite(content, &url);
+                }
+                if let Some(ref mut summary) = document.metadata.summary {
+                    summary.html = rewriter.rewrite(&summary.html, &url);
+                }
+            }
+        }
 
-        Ok(())
+        i18n::link_translations(&mut documents);
+        sections::link_neighbours(&mut documents, &self.settings);
+        sections::link_breadcrumbs(&mut documents, &self.settings);
+        related::link_related(&mut documents, &self.settings.related);
+
+        self.documents = documents
+            .iter()
+            .map(|document| {
+                (
+                    document.metadata.url.clone(),
+                    Arc::new(document.metadata.clone()),
+                )
+            })
+            .collect();
+
+        let mut catalog = Catalog::new(self.documents.values().cloned(), &self.settings);
+        catalog.collections = collections::collect(&self.settings, &catalog.documents);
+        let generated = self.run_generators(&catalog.collections)?;
+        catalog.data = data;
+        catalog.assets = manifest;
+        catalog.images = processed_images;
+
+        // Only the documents listed by the indexes showing their content
+        let listed: HashSet<&str> = generated
+            .iter()
+            .filter(|index| self.shows_full_content(index))
+            .flat_map(|index| match index.content {
+                DocumentContent::Index { ref documents } => documents.as_slice(),
+                _ => &[],
+            })
+            .map(|metadata| metadata.url.as_str())
+            .collect();
+        let contents: Contents = documents
+            .iter()
+            .filter(|document| listed.contains(document.metadata.url.as_str()))
+            .filter_map(|document| match document.content {
+                DocumentContent::Text { ref content } => {
+                    Some((document.metadata.url.as_str(), content.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let page_context = PageContext::new(&catalog).map_err(|err| Error::Render {
+            cause: Box::new(err),
+        })?;
+
+        let this = &*self;
+        let rendered: Vec<Result<()>> = documents
+            .par_iter()
+            .map_init(
+                || page_context.clone(),
+                |page_context, document| {
+                    this.build_document(&catalog, &contents, page_context, document)
+                },
+            )
+            .collect();
+
+        for err in rendered.into_iter().filter_map(Result::err) {
+            self.report_failure(err);
+        }
+
+        generated
+            .par_iter()
+            .map_init(
+                || page_context.clone(),
+                |page_context, document| {
+                    this.build_document(&catalog, &contents, page_context, document)
+                },
+            )
+            .collect::<Vec<Result<()>>>()
+            .into_iter()
+            .collect()
     }
 }
 
+/// Checks whether an entry of a source directory is read, using the default
+/// include and exclude rules of its directory.
+#[deprecated(note = "use the `compiler.include` and `compiler.exclude` settings")]
 pub fn filter_entry(entry: &DirEntry) -> bool {
-    let file_type = entry.file_type();
+    let dir = entry.path().parent().unwrap_or_else(|| entry.path());
+    sources::SourceFilter::new(&Settings::with_working_directory(dir))
+        .map(|filter| filter.accepts_entry(entry))
+        .unwrap_or(false)
+}
 
-    if file_type.is_dir() || file_type.is_file() {
-        utils::is_public(&entry.path())
-    } else {
-        false
+#[test]
+#[allow(deprecated)]
+fn test_filter_entry() {
+    let dir = utils::TempDir::new("filter-entry");
+    for file in &["page.md", "_draft.md", ".hidden.md"] {
+        fs::write(dir.join(file), "").unwrap();
     }
+
+    let accepted: Vec<String> = WalkDir::new(&*dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(filter_entry)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(accepted, ["page.md"]);
+}
+
+#[test]
+fn test_fingerprinted_assets_keep_their_paths() {
+    let dir = utils::TempDir::new("fingerprint");
+    let source_dir = dir.join("source");
+    fs::create_dir_all(source_dir.join("css")).unwrap();
+    fs::create_dir_all(source_dir.join("img")).unwrap();
+    fs::write(
+        source_dir.join("css/style.css"),
+        "body { background: url(../img/bg.png); }",
+    )
+    .unwrap();
+    fs::write(source_dir.join("img/bg.png"), "not really an image").unwrap();
+
+    let mut settings = Settings {
+        output_dir: dir.join("output"),
+        cache_dir: Some(dir.join("cache")),
+        ..Settings::with_working_directory(&source_dir)
+    };
+    settings.assets.fingerprint = true;
+    settings.assets.directories = vec!["css".into(), "img".into()];
+    Compiler::new(&settings).run().unwrap();
+
+    let stylesheet = settings.output_dir.join("css/style.css");
+    let css = fs::read_to_string(&stylesheet).unwrap();
+    let start = css.find("url(").unwrap() + "url(".len();
+    let end = start + css[start..].find(')').unwrap();
+    assert!(stylesheet
+        .parent()
+        .unwrap()
+        .join(&css[start..end])
+        .is_file());
+
+    let manifest: BTreeMap<String, String> =
+        serde_json::from_slice(&fs::read(settings.output_dir.join("assets.json")).unwrap())
+            .unwrap();
+    let fingerprinted = settings.output_dir.join(&manifest["css/style.css"]);
+    assert_eq!(fs::read_to_string(fingerprinted).unwrap(), css);
+}
+
+#[test]
+fn test_keep_outputs_of_failed_documents() {
+    let dir = utils::TempDir::new("failed");
+    let source_dir = dir.join("source");
+    fs::create_dir_all(source_dir.join("_layouts")).unwrap();
+    fs::write(
+        source_dir.join("_layouts/page.html.hbs"),
+        "{{#if page.extra.broken}}{{date \"never\" format=\"%Y\"}}{{/if}}{{page.title}}",
+    )
+    .unwrap();
+    fs::write(source_dir.join("good.md"), "Title: Good\n\nGood").unwrap();
+    fs::write(source_dir.join("bad.md"), "Title: Bad\n\nBad").unwrap();
+
+    let settings = Settings {
+        output_dir: dir.join("output"),
+        cache_dir: Some(dir.join("cache")),
+        ..Settings::with_working_directory(&source_dir)
+    };
+    let build = || Compiler::new(&settings).run();
+    let exists = |file: &str| settings.output_dir.join(file).exists();
+
+    build().unwrap();
+    assert!(exists("good.html") && exists("bad.html"));
+
+    fs::write(source_dir.join("bad.md"), "Title: Bad\nBroken: true\n\nBad").unwrap();
+    build().unwrap();
+    assert!(
+        exists("bad.html"),
+        "the output of a failed document was removed"
+    );
+
+    fs::remove_file(source_dir.join("bad.md")).unwrap();
+    build().unwrap();
+    assert!(
+        !exists("bad.html"),
+        "the output of a deleted document was kept"
+    );
 }
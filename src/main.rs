@@ -76,6 +76,17 @@ fn main() {
 
     let mut opts = Options::new();
     opts.optopt("s", "settings", "use an alternate config file", "FILE");
+    opts.optopt(
+        "j",
+        "jobs",
+        "number of threads used to build the website (default: one per CPU)",
+        "N",
+    );
+    opts.optflag(
+        "c",
+        "clean",
+        "remove the content of the output directory before the build",
+    );
     opts.optflag(
         "i",
         "init",
@@ -85,14 +96,12 @@ fn main() {
     opts.optflag("V", "version", "output version information and exit");
     opts.optflag("q", "quiet", "only display error messages");
 
-    if !cfg!(ndebug) {
-        opts.optflag("D", "debug", "display debug information");
-    }
+    opts.optflag("D", "debug", "display debug information");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(f) => {
-            let _ = writeln!(&mut io::stderr(), "{}", f.to_string());
+            let _ = writeln!(&mut io::stderr(), "{}", f);
             process::exit(1);
         }
     };
@@ -150,6 +159,20 @@ fn main() {
         settings.output_dir = path.clone();
     }
 
+    if let Some(jobs) = matches.opt_str("jobs") {
+        settings.jobs = match jobs.parse() {
+            Ok(n) => n,
+            Err(_) => {
+                error!("Invalid number of jobs: \"{}\"", jobs);
+                process::exit(1);
+            }
+        };
+    }
+
+    if matches.opt_present("clean") {
+        settings.clean = true;
+    }
+
     if matches.opt_present("init") {
         if matches.opt_present("settings") {
             error!("Option \"--settings\" can't be used with \"--init\".");
@@ -171,7 +194,7 @@ fn read_settings<P: AsRef<Path>>(
     let default_file = cwd.as_ref().join(SETTINGS_FILE);
     let settings_file = alternate_file.map(|p| p.as_ref()).unwrap_or(&default_file);
 
-    if is_file(&settings_file) {
+    if is_file(settings_file) {
         info!("Loading settings from {}.", settings_file.display());
         Settings::from_file(&settings_file)
     } else {
@@ -211,15 +234,15 @@ fn unpack_files<P: AsRef<Path>>(files: &[(&str, &[u8])], dest: &P) -> Result<()>
             info!("Skipping {} : the file already exists", dest_file.display());
         } else {
             let parent_dir = dest_file.parent().ok_or(Error::Settings {
-                    message: format!("\"{}\" is not a valid directory", dest.display()),
-                })?;
+                message: format!("\"{}\" is not a valid directory", dest.display()),
+            })?;
 
             debug!("Creating directory {} …", parent_dir.display());
-            fs::create_dir_all(&parent_dir)?;
+            fs::create_dir_all(parent_dir)?;
 
             info!("Creating {} …", current_file.0);
             let mut fh = fs::File::create(&dest_file)?;
-            fh.write(current_file.1)?;
+            fh.write_all(current_file.1)?;
         }
 
         unpack_files(&files[1..], &dest)
@@ -0,0 +1,259 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::document::DocumentMetadata;
+use super::metadata::read_metadata_list;
+use super::Settings;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub type Menus = BTreeMap<String, Vec<MenuEntry>>;
+
+/// An entry of a navigation menu.
+#[derive(Debug, Clone, Serialize)]
+pub struct MenuEntry {
+    pub name: String,
+    pub url: String,
+    pub weight: i64,
+    #[serde(skip)]
+    pub identifier: String,
+    #[serde(skip)]
+    pub parent: Option<String>,
+    #[serde(skip)]
+    pub language: Option<String>,
+    /// Whether this entry or one of its children links to the current page
+    pub active: bool,
+    pub children: Vec<MenuEntry>,
+}
+
+/// Normalizes an URL of the website so that it can be compared to the URL of
+/// a document.
+fn normalize_url(url: &str, site_url: &str) -> String {
+    let url = if !site_url.is_empty() && url.starts_with(site_url) {
+        &url[site_url.len()..]
+    } else {
+        url
+    };
+    let url = url.trim_start_matches('/');
+
+    if url.is_empty() || url.ends_with('/') {
+        format!("{}index.html", url)
+    } else {
+        url.into()
+    }
+}
+
+/// Reads the menu entries declared in the metadata of a document with the
+/// `menu`, `menu_weight` and `menu_parent` fields.
+fn document_entries(document: &DocumentMetadata, site_url: &str) -> Vec<(String, MenuEntry)> {
+    let menus: Vec<String> = match document.extra.get("menu") {
+        Some(raw) => read_metadata_list(raw)
+            .map(|v| v.into())
+            .unwrap_or_default(),
+        None => return Vec::new(),
+    };
+
+    let weight = match document.extra.get("menu_weight").map(|w| w.parse()) {
+        None => 0,
+        Some(Ok(weight)) => weight,
+        Some(Err(_)) => {
+            warn!("{}: `menu_weight` must be an integer", document.url);
+            0
+        }
+    };
+
+    menus
+        .into_iter()
+        .map(|menu| {
+            let entry = MenuEntry {
+                name: document.title.clone(),
+                url: format!("{}{}", site_url, document.url),
+                weight,
+                identifier: document.url.clone(),
+                parent: document.extra.get("menu_parent").cloned(),
+                language: document.language.clone(),
+                active: false,
+                children: Vec::new(),
+            };
+            (menu, entry)
+        })
+        .collect()
+}
+
+fn build_tree(entries: &mut Vec<MenuEntry>, parent: Option<&str>) -> Vec<MenuEntry> {
+    let (mut children, rest): (Vec<MenuEntry>, Vec<MenuEntry>) = entries
+        .drain(..)
+        .partition(|entry| entry.parent.as_deref() == parent);
+    *entries = rest;
+
+    for child in children.iter_mut() {
+        child.children = build_tree(entries, Some(&child.identifier));
+    }
+
+    children.sort_by(|a, b| a.weight.cmp(&b.weight).then_with(|| a.name.cmp(&b.name)));
+    children
+}
+
+/// Builds the menus declared in the settings and in the metadata of the
+/// documents.
+pub fn build_menus(settings: &Settings, documents: &[Arc<DocumentMetadata>]) -> Menus {
+    let mut entries: BTreeMap<String, Vec<MenuEntry>> = BTreeMap::new();
+
+    for (menu, menu_entries) in settings.menus.iter() {
+        entries
+            .entry(menu.clone())
+            .or_default()
+            .extend(menu_entries.iter().map(|entry| {
+                MenuEntry {
+                    name: entry.name.clone(),
+                    url: entry.url.clone(),
+                    weight: entry.weight,
+                    identifier: entry
+                        .identifier
+                        .clone()
+                        .unwrap_or_else(|| entry.name.clone()),
+                    parent: entry.parent.clone(),
+                    language: entry.language.clone(),
+                    active: false,
+                    children: Vec::new(),
+                }
+            }));
+    }
+
+    for document in documents.iter() {
+        for (menu, entry) in document_entries(document, &settings.site_url) {
+            entries.entry(menu).or_default().push(entry);
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|(menu, mut menu_entries)| {
+            let mut tree = build_tree(&mut menu_entries, None);
+            for orphan in menu_entries.iter() {
+                warn!(
+                    "Menu \"{}\": the parent of \"{}\" does not exist",
+                    menu, orphan.name
+                );
+            }
+            tree.extend(menu_entries.into_iter().map(|entry| MenuEntry {
+                parent: None,
+                ..entry
+            }));
+            (menu, tree)
+        })
+        .collect()
+}
+
+fn localize_entries(
+    entries: &[MenuEntry],
+    url: &str,
+    language: Option<&str>,
+    site_url: &str,
+) -> Vec<MenuEntry> {
+    entries
+        .iter()
+        .filter(|entry| entry.language.is_none() || entry.language.as_deref() == language)
+        .map(|entry| {
+            let children = localize_entries(&entry.children, url, language, site_url);
+            let active = normalize_url(&entry.url, site_url) == url
+                || children.iter().any(|child| child.active);
+            MenuEntry {
+                active,
+                children,
+                ..entry.clone()
+            }
+        })
+        .collect()
+}
+
+/// Returns the menus as displayed on the page at `url`, written in `language`.
+pub fn localize(menus: &Menus, url: &str, language: Option<&str>, site_url: &str) -> Menus {
+    let url = normalize_url(url, site_url);
+    menus
+        .iter()
+        .map(|(menu, entries)| {
+            (
+                menu.clone(),
+                localize_entries(entries, &url, language, site_url),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_normalize_url() {
+    assert_eq!(normalize_url("/", "/"), "index.html");
+    assert_eq!(normalize_url("/blog/", "/"), "blog/index.html");
+    assert_eq!(normalize_url("/about.html", "/"), "about.html");
+    assert_eq!(normalize_url("about.html", "/"), "about.html");
+    assert_eq!(normalize_url("/site/about.html", "/site/"), "about.html");
+}
+
+#[test]
+fn test_menus() {
+    use crate::settings::MenuEntrySettings;
+
+    let mut settings = Settings::default();
+    settings.menus.insert(
+        "main".into(),
+        vec![
+            MenuEntrySettings {
+                name: "Home".into(),
+                url: "/".into(),
+                weight: -10,
+                ..MenuEntrySettings::default()
+            },
+            MenuEntrySettings {
+                name: "Blog".into(),
+                url: "/blog/".into(),
+                identifier: Some("blog".into()),
+                ..MenuEntrySettings::default()
+            },
+        ],
+    );
+
+    let mut post = DocumentMetadata {
+        url: "blog/post.html".into(),
+        title: "Post".into(),
+        ..DocumentMetadata::default()
+    };
+    post.extra.insert("menu".into(), "main".into());
+    post.extra.insert("menu_parent".into(), "blog".into());
+    let mut about = DocumentMetadata {
+        url: "about.html".into(),
+        title: "About".into(),
+        ..DocumentMetadata::default()
+    };
+    about.extra.insert("menu".into(), "main, footer".into());
+    about.extra.insert("menu_weight".into(), "5".into());
+
+    let menus = build_menus(&settings, &[Arc::new(post), Arc::new(about)]);
+    let names = |entries: &[MenuEntry]| -> Vec<String> {
+        entries.iter().map(|entry| entry.name.clone()).collect()
+    };
+    assert_eq!(names(&menus["main"]), ["Home", "Blog", "About"]);
+    assert_eq!(names(&menus["main"][1].children), ["Post"]);
+    assert_eq!(names(&menus["footer"]), ["About"]);
+
+    let localized = localize(&menus, "blog/post.html", None, "/");
+    let main = &localized["main"];
+    assert!(!main[0].active);
+    assert!(main[1].active);
+    assert!(main[1].children[0].active);
+    assert!(!main[2].active);
+}
@@ -16,6 +16,7 @@
 
 use super::utils::DateTime;
 use super::{Error, Result};
+use chrono::FixedOffset;
 use std::collections::BTreeMap;
 use std::iter::{FromIterator, Iterator};
 
@@ -32,7 +33,7 @@ pub enum Value {
     Map(BTreeMap<String, Value>),
 }
 
-impl<'l> From<&'l str> for Value {
+impl From<&str> for Value {
     fn from(string: &str) -> Value {
         Value::String(string.into())
     }
@@ -79,21 +80,21 @@ macro_rules! impl_from (
 );
 
 pub trait OptAsRef<T> {
-    fn as_ref<'l>(&'l self) -> Option<&'l T>;
-    fn as_mut<'l>(&'l mut self) -> Option<&'l mut T>;
+    fn as_ref(&self) -> Option<&T>;
+    fn as_mut(&mut self) -> Option<&mut T>;
 }
 
 macro_rules! impl_opt_as_ref(
     ($to: ty, $variant: path) => (
         impl OptAsRef<$to> for Value {
-            fn as_ref<'l>(&'l self) -> Option<&'l $to> {
+            fn as_ref(&self) -> Option<&$to> {
                 match *self {
                     $variant(ref value) => Some(value),
                     _ => None,
                 }
             }
 
-            fn as_mut<'l>(&'l mut self) -> Option<&'l mut $to> {
+            fn as_mut(&mut self) -> Option<&mut $to> {
                 match *self {
                     $variant(ref mut value) => Some(value),
                     _ => None,
@@ -105,9 +106,9 @@ macro_rules! impl_opt_as_ref(
 
 macro_rules! impl_into (
     ($to: ty, $variant: path) => (
-        impl Into<Option<$to>> for Value {
-            fn into(self) -> Option<$to> {
-                match self {
+        impl From<Value> for Option<$to> {
+            fn from(value: Value) -> Option<$to> {
+                match value {
                     $variant(e) => Some(e),
                     _ => None,
                 }
@@ -116,12 +117,12 @@ macro_rules! impl_into (
     )
 );
 
-impl<T> Into<Vec<T>> for Value
+impl<T> From<Value> for Vec<T>
 where
-    Value: Into<Option<T>>,
+    Option<T>: From<Value>,
 {
-    fn into(self) -> Vec<T> {
-        match self {
+    fn from(value: Value) -> Vec<T> {
+        match value {
             Value::Vec(v) => v.into_iter().filter_map(|i| i.into()).collect(),
             _ => Vec::new(),
         }
@@ -157,6 +158,7 @@ impl_into!(BTreeMap<String, Value>, Value::Map);
 
 pub trait Field {
     fn get_name(&self) -> &'static str;
+    #[allow(clippy::wrong_self_convention)]
     fn from_raw(&self, raw: &str) -> Result<Value>;
 
     #[inline]
@@ -165,7 +167,8 @@ pub trait Field {
     }
 }
 
-fn read_metadata_list(metadata: &str) -> Result<Value> {
+/// Reads a list of values separated by semicolons or by commas.
+pub fn read_metadata_list(metadata: &str) -> Result<Value> {
     let sep = if metadata.find(';').is_some() {
         ';'
     } else {
@@ -200,21 +203,61 @@ fn test_text_from_raw() {
     assert!(result == Some(String::from("foo")));
 }
 
+/// A date field; dates without an UTC offset are read as UTC.
 pub struct Date(pub &'static str);
 unsafe impl Sync for Date {}
 
+impl Date {
+    /// Returns a date field reading the dates without an UTC offset in the
+    /// given timezone.
+    pub fn with_timezone(name: &'static str, timezone: FixedOffset) -> LocalDate {
+        LocalDate(name, timezone)
+    }
+}
+
 impl Field for Date {
     fn get_name(&self) -> &'static str {
         self.0
     }
 
     fn from_raw(&self, raw: &str) -> Result<Value> {
-        DateTime::from_string(raw)
+        LocalDate(self.0, FixedOffset::east(0)).from_raw(raw)
+    }
+}
+
+/// A date field; dates without an UTC offset are read in the given timezone.
+pub struct LocalDate(pub &'static str, pub FixedOffset);
+unsafe impl Sync for LocalDate {}
+
+impl Field for LocalDate {
+    fn get_name(&self) -> &'static str {
+        self.0
+    }
+
+    fn from_raw(&self, raw: &str) -> Result<Value> {
+        DateTime::parse(raw, &self.1)
             .ok_or_else(|| Error::InvalidDate { date: raw.into() })
             .map(Value::from)
     }
 }
 
+#[test]
+fn test_date_from_raw() {
+    let date = |field: &dyn Field, raw: &str| -> Option<DateTime> {
+        field.from_raw(raw).ok().and_then(|value| value.into())
+    };
+    let local = Date::with_timezone("created", FixedOffset::east(3600));
+    assert_eq!(
+        date(&local, "2020-05-01 14:00"),
+        DateTime::from_string("2020-05-01T14:00:00+01:00")
+    );
+    assert_eq!(
+        date(&Date("created"), "2020-05-01 14:00"),
+        DateTime::from_string("2020-05-01T14:00:00+00:00")
+    );
+    assert!(local.from_raw("not a date").is_err());
+}
+
 pub struct Keywords(pub &'static str);
 unsafe impl Sync for Keywords {}
 
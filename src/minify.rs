@@ -0,0 +1,125 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::settings::MinifySettings;
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use minify_js::{Session, TopLevelMode};
+use std::path::Path;
+
+/// Minifies a stylesheet.
+pub fn minify_css(code: &str) -> Result<String, String> {
+    let mut stylesheet =
+        StyleSheet::parse(code, ParserOptions::default()).map_err(|err| err.to_string())?;
+    stylesheet
+        .minify(MinifyOptions::default())
+        .map_err(|err| err.to_string())?;
+    stylesheet
+        .to_css(PrinterOptions {
+            minify: true,
+            ..PrinterOptions::default()
+        })
+        .map(|output| output.code)
+        .map_err(|err| err.to_string())
+}
+
+/// Minifies a script.
+pub fn minify_js(code: &[u8]) -> Result<Vec<u8>, String> {
+    let session = Session::new();
+    let mut output = Vec::with_capacity(code.len());
+    minify_js::minify(&session, TopLevelMode::Global, code, &mut output)
+        .map_err(|err| format!("{:?}", err))?;
+    Ok(output)
+}
+
+/// Minifies a rendered page, along with its inline styles and scripts when
+/// the stylesheets and scripts are minified.
+pub fn minify_html(html: &str, settings: &MinifySettings) -> Vec<u8> {
+    let mut cfg = minify_html::Cfg::spec_compliant();
+    cfg.keep_closing_tags = true;
+    cfg.keep_html_and_head_opening_tags = true;
+    cfg.minify_css = settings.css;
+    cfg.minify_js = settings.js;
+    minify_html::minify(html.as_bytes(), &cfg)
+}
+
+/// Checks whether a static file is minified.
+pub fn is_minified(settings: &MinifySettings, relpath: &Path) -> bool {
+    match relpath.extension().and_then(|extension| extension.to_str()) {
+        Some("css") => settings.css,
+        Some("js") => settings.js,
+        _ => false,
+    }
+}
+
+/// Minifies a stylesheet or a script, or returns it unchanged when it cannot
+/// be parsed.
+pub fn minify_file(relpath: &Path, content: Vec<u8>) -> Vec<u8> {
+    let minified = match relpath.extension().and_then(|extension| extension.to_str()) {
+        Some("css") => String::from_utf8(content.clone())
+            .map_err(|err| err.to_string())
+            .and_then(|code| minify_css(&code))
+            .map(String::into_bytes),
+        Some("js") => minify_js(&content),
+        _ => return content,
+    };
+
+    match minified {
+        Ok(minified) => minified,
+        Err(err) => {
+            warn!("Could not minify {}: {}", relpath.display(), err);
+            content
+        }
+    }
+}
+
+#[test]
+fn test_minify_css() {
+    assert_eq!(
+        minify_css("a {\n  color: #ff0000;\n}\n\n/* Comment */\nb { margin: 0px }\n").unwrap(),
+        "a{color:red}b{margin:0}"
+    );
+    assert!(minify_css("a { color: red").is_ok());
+}
+
+#[test]
+fn test_minify_js() {
+    let minified =
+        minify_js(b"function add(first, second) {\n  return first + second;\n}\n").unwrap();
+    assert!(
+        minified.len() < 40,
+        "{}",
+        String::from_utf8_lossy(&minified)
+    );
+    assert!(minify_js(b"function (").is_err());
+
+    let path = Path::new("js/broken.js");
+    assert_eq!(minify_file(path, b"function (".to_vec()), b"function (");
+}
+
+#[test]
+fn test_minify_html() {
+    let settings = MinifySettings {
+        html: true,
+        css: true,
+        js: false,
+    };
+    let html = "<html>\n  <head>\n    <style>a { color: #ff0000 }</style>\n  </head>\n  \
+                <body>\n    <p class=\"a\">  Some   text  </p>\n  </body>\n</html>\n";
+    assert_eq!(
+        String::from_utf8(minify_html(html, &settings)).unwrap(),
+        "<html><head><style>a{color:red}</style></head><body><p class=a>Some text</p></body></html>"
+    );
+}
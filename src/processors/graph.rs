@@ -0,0 +1,173 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::assets::{content_hash, url_path};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The files read and written when processing a source file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    /// The hash of the content of the source file and of its dependencies,
+    /// indexed by their paths relative to the source directory
+    pub inputs: BTreeMap<String, String>,
+    /// The paths of the written files, relative to the output directory
+    pub outputs: Vec<String>,
+    /// The fingerprinted assets among the written files
+    pub assets: Vec<(String, String)>,
+}
+
+impl Node {
+    /// Describes the processing of a file, hashing its inputs as they are
+    /// now.
+    pub fn new(source_dir: &Path, inputs: &[PathBuf], outputs: Vec<String>) -> Node {
+        let inputs = inputs
+            .iter()
+            .map(|path| {
+                let hash = fs::read(path)
+                    .map(|content| content_hash(&content))
+                    .unwrap_or_default();
+                (input_name(source_dir, path), hash)
+            })
+            .collect();
+
+        Node {
+            inputs,
+            outputs,
+            assets: Vec::new(),
+        }
+    }
+
+    /// Checks whether none of the inputs changed and all the outputs still
+    /// exist.
+    fn is_fresh(&self, source_dir: &Path, output_dir: &Path) -> bool {
+        let unchanged = self.inputs.iter().all(|(name, hash)| {
+            fs::read(source_dir.join(name)).is_ok_and(|content| content_hash(&content) == *hash)
+        });
+        unchanged
+            && self
+                .outputs
+                .iter()
+                .all(|output| output_dir.join(output).is_file())
+    }
+}
+
+/// Returns the path of an input relative to the source directory, or its
+/// full path when it is outside of it.
+fn input_name(source_dir: &Path, path: &Path) -> String {
+    let relpath = path.strip_prefix(source_dir).ok().or_else(|| {
+        let source_dir = fs::canonicalize(source_dir).ok()?;
+        path.strip_prefix(source_dir).ok()
+    });
+    match relpath {
+        Some(relpath) => url_path(relpath),
+        None => path.to_string_lossy().into(),
+    }
+}
+
+/// The dependency graph of the processed files, kept between the builds to
+/// process again only the files whose inputs changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Graph {
+    /// The hash of the settings used for the build
+    settings: String,
+    /// The processed files, indexed by their paths relative to the source
+    /// directory
+    nodes: BTreeMap<String, Node>,
+}
+
+impl Graph {
+    pub fn new(settings: String) -> Graph {
+        Graph {
+            settings,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the graph of the previous build. It is discarded when it is
+    /// missing, invalid or was built with other settings.
+    pub fn load(path: &Path, settings: String) -> Graph {
+        fs::read(path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<Graph>(&content).ok())
+            .filter(|graph| graph.settings == settings)
+            .unwrap_or_else(|| Graph::new(settings))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn insert(&mut self, source: String, node: Node) {
+        self.nodes.insert(source, node);
+    }
+
+    /// Returns the node of a file if it does not need to be processed again.
+    pub fn fresh(&self, source: &str, source_dir: &Path, output_dir: &Path) -> Option<&Node> {
+        self.nodes
+            .get(source)
+            .filter(|node| node.is_fresh(source_dir, output_dir))
+    }
+}
+
+#[test]
+fn test_graph() {
+    let dir = crate::utils::TempDir::new("graph");
+    let (source_dir, output_dir) = (dir.join("source"), dir.join("output"));
+    fs::create_dir_all(source_dir.join("css")).unwrap();
+    fs::create_dir_all(output_dir.join("css")).unwrap();
+    fs::write(source_dir.join("css/main.scss"), "@use \"colors\";").unwrap();
+    fs::write(source_dir.join("css/_colors.scss"), "$main: red;").unwrap();
+    fs::write(output_dir.join("css/main.css"), "").unwrap();
+
+    let inputs = [
+        source_dir.join("css/main.scss"),
+        source_dir.join("css/_colors.scss"),
+    ];
+    let mut graph = Graph::new("settings".into());
+    let node = Node::new(&source_dir, &inputs, vec!["css/main.css".into()]);
+    graph.insert("css/main.scss".into(), node);
+
+    let path = dir.join("graph.json");
+    graph.save(&path).unwrap();
+    assert_eq!(Graph::load(&path, "settings".into()), graph);
+    assert_eq!(
+        Graph::load(&path, "other".into()),
+        Graph::new("other".into())
+    );
+
+    let fresh = |graph: &Graph| {
+        graph
+            .fresh("css/main.scss", &source_dir, &output_dir)
+            .is_some()
+    };
+    assert!(fresh(&graph));
+    fs::write(source_dir.join("css/_colors.scss"), "$main: blue;").unwrap();
+    assert!(!fresh(&graph));
+    fs::write(source_dir.join("css/_colors.scss"), "$main: red;").unwrap();
+    fs::remove_file(output_dir.join("css/main.css")).unwrap();
+    assert!(!fresh(&graph));
+}
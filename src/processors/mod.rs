@@ -0,0 +1,64 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{Result, Settings};
+use std::path::{Path, PathBuf};
+
+mod graph;
+mod sass;
+pub use self::graph::{Graph, Node};
+pub use self::sass::SassProcessor;
+
+/// A file written by a processor, relative to the output directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+}
+
+/// The result of the processing of a file.
+#[derive(Debug, Default)]
+pub struct Processed {
+    pub outputs: Vec<Output>,
+    /// The other files read while processing the file, which cause it to be
+    /// processed again when they change
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Transforms the files which are not documents into one or more output
+/// files, instead of copying them.
+pub trait Processor: Send + Sync {
+    fn new(settings: &Settings) -> Self
+    where
+        Self: Sized;
+    /// The extensions (`scss`) and the patterns (`img/**/*.svg`) of the files
+    /// handled by the processor, relative to the source directory
+    fn patterns(&self) -> Vec<String>;
+    fn process(&self, path: &Path, relpath: &Path) -> Result<Processed>;
+}
+
+/// Checks whether a pattern is an extension rather than a glob.
+pub fn is_extension(pattern: &str) -> bool {
+    !pattern.contains(['/', '*', '?', '[', '{', '.'])
+}
+
+#[test]
+fn test_is_extension() {
+    assert!(is_extension("scss"));
+    assert!(!is_extension("*.scss"));
+    assert!(!is_extension("css/main.scss"));
+    assert!(!is_extension("img/**/*.svg"));
+}
@@ -0,0 +1,51 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::{sass, Result, Settings};
+use super::{Output, Processed, Processor};
+use std::path::Path;
+
+/// Compiles the Sass stylesheets to CSS.
+pub struct SassProcessor {
+    settings: Settings,
+}
+
+impl Processor for SassProcessor {
+    fn new(settings: &Settings) -> SassProcessor {
+        SassProcessor {
+            settings: settings.clone(),
+        }
+    }
+
+    fn patterns(&self) -> Vec<String> {
+        if self.settings.sass.enabled {
+            vec!["scss".into(), "sass".into()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn process(&self, path: &Path, relpath: &Path) -> Result<Processed> {
+        let stylesheet = sass::compile(path, &self.settings)?;
+        Ok(Processed {
+            outputs: vec![Output {
+                path: sass::output_path(relpath),
+                content: stylesheet.css.into_bytes(),
+            }],
+            dependencies: stylesheet.imports,
+        })
+    }
+}
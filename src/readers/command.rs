@@ -0,0 +1,249 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::settings::{CommandInput, CommandReaderSettings};
+use super::super::{Error, Result, Settings};
+use super::{Content, Metadata, Reader};
+use serde_json::{Deserializer, Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::{error, result, thread};
+
+/// Converts documents with the external commands declared in the `[readers]`
+/// section of the settings.
+///
+/// A command writes the HTML body of the document to its standard output,
+/// optionally preceded by a JSON object holding the metadata of the document.
+#[derive(Debug, Clone)]
+pub struct CommandReader {
+    commands: HashMap<String, (String, Arc<CommandReaderSettings>)>,
+}
+
+/// The failure of an external command.
+#[derive(Debug)]
+pub struct CommandError {
+    /// The name of the reader in the settings
+    pub reader: String,
+    pub message: String,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the reader `{}` failed: {}", self.reader, self.message)
+    }
+}
+
+impl error::Error for CommandError {}
+
+impl CommandReader {
+    /// The extensions of the documents read by the configured commands.
+    pub fn configured_extensions(&self) -> Vec<String> {
+        self.commands.keys().cloned().collect()
+    }
+
+    fn run(&self, name: &str, settings: &CommandReaderSettings, path: &Path) -> Result<String> {
+        let error = |message: String| Error::Reader {
+            path: path.into(),
+            cause: Box::new(CommandError {
+                reader: name.into(),
+                message,
+            }),
+        };
+
+        let mut arguments: Vec<String> = settings.command[1..].to_vec();
+        if settings.input == CommandInput::Argument {
+            let path = path.to_string_lossy();
+            if arguments.iter().any(|argument| argument.contains("{path}")) {
+                for argument in arguments.iter_mut() {
+                    *argument = argument.replace("{path}", &path);
+                }
+            } else {
+                arguments.push(path.into());
+            }
+        }
+
+        let input = match settings.input {
+            CommandInput::Stdin => Some(fs::read(path).map_err(|err| Error::Reader {
+                path: path.into(),
+                cause: Box::new(err),
+            })?),
+            CommandInput::Argument => None,
+        };
+
+        let mut child = Command::new(&settings.command[0])
+            .args(&arguments)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| error(err.to_string()))?;
+
+        // The input is written from another thread, so that a command writing
+        // its output before reading all of its input cannot block.
+        let writer = match (input, child.stdin.take()) {
+            (Some(input), Some(mut stdin)) => Some(thread::spawn(move || stdin.write_all(&input))),
+            _ => None,
+        };
+
+        let output = child
+            .wait_with_output()
+            .map_err(|err| error(err.to_string()))?;
+        if let Some(writer) = writer {
+            // A command may exit without reading all of its input.
+            let _ = writer.join();
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(error(format!("{}\n{}", output.status, stderr.trim_end())));
+        }
+        String::from_utf8(output.stdout).map_err(|err| error(err.to_string()))
+    }
+}
+
+/// Converts the value of a metadata field to the text read by the other
+/// readers.
+fn metadata_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value.clone()),
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(metadata_value)
+                .collect::<Vec<String>>()
+                .join(", "),
+        ),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Splits the output of a command into its metadata and its HTML body.
+fn parse_output(output: &str) -> result::Result<(Content, Metadata), serde_json::Error> {
+    let mut metadata = Metadata::new();
+    let mut body = output;
+
+    if output.trim_start().starts_with('{') {
+        let mut values = Deserializer::from_str(output).into_iter::<Map<String, Value>>();
+        if let Some(fields) = values.next() {
+            for (key, value) in fields? {
+                if let Some(value) = metadata_value(&value) {
+                    metadata.insert(key.to_lowercase(), value);
+                }
+            }
+        }
+        body = &output[values.byte_offset()..];
+    }
+
+    Ok((Content::from_html(body.trim().into()), metadata))
+}
+
+impl Reader for CommandReader {
+    fn new(settings: &Settings) -> CommandReader {
+        let mut commands = HashMap::new();
+        for (name, reader) in settings.readers.iter() {
+            let reader = Arc::new(reader.clone());
+            for extension in reader.extensions.iter() {
+                commands.insert(extension.clone(), (name.clone(), reader.clone()));
+            }
+        }
+        CommandReader { commands }
+    }
+
+    /// The extensions are read from the settings, see
+    /// `CommandReader::configured_extensions`.
+    fn extensions() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let (name, settings) = self.commands.get(extension).ok_or_else(|| Error::Reader {
+            path: path.into(),
+            cause: format!("no command reads the `.{}` files", extension).into(),
+        })?;
+
+        let output = self.run(name, settings, path)?;
+        parse_output(&output).map_err(|err| Error::Reader {
+            path: path.into(),
+            cause: Box::new(CommandError {
+                reader: name.clone(),
+                message: format!("invalid metadata: {}", err),
+            }),
+        })
+    }
+}
+
+#[test]
+fn test_parse_output() {
+    let (content, metadata) = parse_output(
+        "{\"Title\": \"Foo\", \"keywords\": [\"a\", \"b\"], \"draft\": false, \"x\": null}\n<p>Bar</p>\n",
+    )
+    .unwrap();
+    assert_eq!(metadata.get("title"), Some(&"Foo".into()));
+    assert_eq!(metadata.get("keywords"), Some(&"a, b".into()));
+    assert_eq!(metadata.get("draft"), Some(&"false".into()));
+    assert_eq!(metadata.get("x"), None);
+    assert_eq!(content.html, "<p>Bar</p>");
+
+    let (content, metadata) = parse_output("<p>Bar</p>").unwrap();
+    assert!(metadata.is_empty());
+    assert_eq!(content.html, "<p>Bar</p>");
+
+    assert!(parse_output("{\"title\": ").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_reader() {
+    let mut settings = Settings::default();
+    let reader = |command: &[&str], input: CommandInput| CommandReaderSettings {
+        command: command.iter().map(|&arg| String::from(arg)).collect(),
+        extensions: vec!["toml".into()],
+        input,
+    };
+    let path = Path::new("Cargo.toml");
+
+    settings
+        .readers
+        .insert("cat".into(), reader(&["cat"], CommandInput::Stdin));
+    let (content, _) = CommandReader::new(&settings).load(path).unwrap();
+    assert!(content.html.starts_with("[package]"));
+
+    settings.readers.insert(
+        "cat".into(),
+        reader(
+            &["sh", "-c", "cat \"$1\" >&2; exit 3", "sh"],
+            CommandInput::Argument,
+        ),
+    );
+    let err = CommandReader::new(&settings).load(path).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("the reader `cat` failed"), "{}", message);
+    assert!(message.contains("[package]"), "{}", message);
+}
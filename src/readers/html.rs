@@ -0,0 +1,128 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::{Result, Settings};
+use super::{read_file, Content, Metadata, Reader};
+use regex::Regex;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Reads HTML fragments starting with a comment holding their metadata,
+/// whose first field must be the title:
+///
+/// ```html
+/// <!--
+/// Title: Foo
+/// Created: 2020-05-01
+/// -->
+/// <p>Bar</p>
+/// ```
+///
+/// The other HTML files, including those starting with another comment, are
+/// copied as is.
+#[derive(Debug, Clone)]
+pub struct HtmlReader {
+    title: Regex,
+}
+
+static EXTENSIONS: &[&str] = &["html", "htm"];
+
+/// The number of bytes read to check whether a file has a metadata header
+const HEADER_LENGTH: u64 = 512;
+
+impl HtmlReader {
+    /// Splits a document into its metadata header and its body.
+    fn split_header<'a>(&self, input: &'a str) -> Option<(&'a str, &'a str)> {
+        let input = input.trim_start().strip_prefix("<!--")?;
+        let first_line = input.lines().map(str::trim).find(|line| !line.is_empty())?;
+        if !self.title.is_match(first_line) {
+            return None;
+        }
+
+        match input.find("-->") {
+            Some(end) => Some((&input[..end], &input[end + 3..])),
+            None => Some((input, "")),
+        }
+    }
+}
+
+fn process_html(header: &str, body: &str) -> (Content, Metadata) {
+    let metadata = header
+        .lines()
+        .filter_map(|line| {
+            let mut split = line.splitn(2, ':');
+            match (split.next(), split.next()) {
+                (Some(key), Some(value)) => {
+                    Some((key.trim().to_ascii_lowercase(), String::from(value.trim())))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    (Content::from_html(body.trim().into()), metadata)
+}
+
+impl Reader for HtmlReader {
+    fn new(_settings: &Settings) -> HtmlReader {
+        HtmlReader {
+            title: Regex::new(r"(?i)^title\s*:").unwrap(),
+        }
+    }
+
+    fn extensions() -> &'static [&'static str] {
+        EXTENSIONS
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        let mut start = Vec::new();
+        File::open(path)
+            .and_then(|fd| fd.take(HEADER_LENGTH).read_to_end(&mut start))
+            .map(|_| {
+                self.split_header(&String::from_utf8_lossy(&start))
+                    .is_some()
+            })
+            .unwrap_or(false)
+    }
+
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)> {
+        let input = read_file(path)?;
+        let (header, body) = self.split_header(&input).unwrap_or(("", &input));
+        Ok(process_html(header, body))
+    }
+}
+
+#[test]
+fn test_html_reader() {
+    let reader = HtmlReader::new(&Settings::default());
+    let input = "<!--\nTitle: Foo\nKeywords: a, b\n-->\n<h2>Bar</h2>\n<p>Baz</p>\n";
+
+    let (header, body) = reader.split_header(input).unwrap();
+    let (content, metadata) = process_html(header, body);
+    assert_eq!(metadata.get("title"), Some(&"Foo".into()));
+    assert_eq!(metadata.get("keywords"), Some(&"a, b".into()));
+    assert_eq!(content.html, "<h2>Bar</h2>\n<p>Baz</p>");
+    assert_eq!(content.headings, ["Bar"]);
+
+    assert!(reader
+        .split_header("<!DOCTYPE html><html></html>")
+        .is_none());
+    assert!(reader.split_header("<!-- a comment -->").is_none());
+    assert!(reader
+        .split_header("<!--\nTODO: fix the layout\n-->\n<p>Bar</p>")
+        .is_none());
+}
@@ -0,0 +1,276 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::{Error, Result, Settings};
+use super::markdown::{process_markdown, render_markdown};
+use super::{escape_html, read_file, Content, Metadata, Reader};
+use regex::Regex;
+use serde_json::Value;
+use std::path::Path;
+
+/// Reads Jupyter notebooks: the Markdown cells are converted like Markdown
+/// documents, the code cells are shown with their outputs.
+///
+/// The title and the metadata are read from the metadata of the notebook, or
+/// else from the first cell when it is written in Markdown.
+#[derive(Debug, Clone)]
+pub struct NotebookReader;
+
+static EXTENSIONS: &[&str] = &["ipynb"];
+
+/// The metadata fields of the notebook read as the fields of the document
+static METADATA_FIELDS: &[(&str, &str)] = &[
+    ("title", "title"),
+    ("date", "created"),
+    ("created", "created"),
+    ("modified", "modified"),
+    ("keywords", "keywords"),
+    ("tags", "keywords"),
+    ("language", "language"),
+    ("summary", "summary"),
+    ("description", "summary"),
+];
+
+impl Reader for NotebookReader {
+    fn new(_settings: &Settings) -> NotebookReader {
+        NotebookReader
+    }
+
+    fn extensions() -> &'static [&'static str] {
+        EXTENSIONS
+    }
+
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)> {
+        let notebook: Value =
+            serde_json::from_str(&read_file(path)?).map_err(|err| Error::Reader {
+                path: path.into(),
+                cause: Box::new(err),
+            })?;
+        Ok(process_notebook(&notebook))
+    }
+}
+
+/// Joins a multiline string of a notebook, stored as a string or as a list of
+/// lines.
+fn read_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Converts a metadata value of the notebook to the text read by the other
+/// readers.
+fn metadata_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null | Value::Object(_) => None,
+        Value::String(text) => Some(text.clone()),
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(metadata_value)
+                .collect::<Vec<String>>()
+                .join(", "),
+        ),
+        value => Some(value.to_string()),
+    }
+}
+
+fn read_metadata(notebook: &Value) -> Metadata {
+    let mut metadata = Metadata::new();
+    let fields = match notebook["metadata"].as_object() {
+        Some(fields) => fields,
+        None => return metadata,
+    };
+
+    for (name, value) in fields.iter() {
+        let name = name.to_lowercase();
+        let key = METADATA_FIELDS
+            .iter()
+            .find(|&&(field, _)| field == name)
+            .map_or(name.as_str(), |&(_, key)| key);
+        if let Some(value) = metadata_value(value) {
+            metadata.insert(key.into(), value);
+        }
+    }
+
+    // The authors are stored as `[{"name": "Jane"}]`
+    if let Some(authors) = fields.get("authors").and_then(Value::as_array) {
+        let names: Vec<&str> = authors
+            .iter()
+            .filter_map(|author| author["name"].as_str().or_else(|| author.as_str()))
+            .collect();
+        metadata.insert("authors".into(), names.join(", "));
+    }
+    metadata
+}
+
+struct Converter {
+    language: String,
+    ansi: Regex,
+}
+
+impl Converter {
+    fn code(&self, source: &str) -> String {
+        format!(
+            "<pre><code class=\"language-{}\">{}</code></pre>",
+            escape_html(&self.language),
+            escape_html(source.trim_end())
+        )
+    }
+
+    fn text_output(&self, text: &str) -> String {
+        let text = self.ansi.replace_all(text, "");
+        format!(
+            "<pre class=\"output\">{}</pre>",
+            escape_html(text.trim_end())
+        )
+    }
+
+    /// Converts the richest representation of an output of a code cell.
+    fn rich_output(&self, data: &Value) -> Option<String> {
+        if let Some(html) = data.get("text/html") {
+            return Some(format!("<div class=\"output\">{}</div>", read_text(html)));
+        }
+        for &format in ["image/png", "image/jpeg", "image/gif"].iter() {
+            if let Some(image) = data.get(format) {
+                let image: String = read_text(image).split_whitespace().collect();
+                return Some(format!(
+                    "<img class=\"output\" src=\"data:{};base64,{}\" alt=\"\" />",
+                    format, image
+                ));
+            }
+        }
+        if let Some(svg) = data.get("image/svg+xml") {
+            return Some(format!("<div class=\"output\">{}</div>", read_text(svg)));
+        }
+        if let Some(markdown) = data.get("text/markdown") {
+            return Some(format!(
+                "<div class=\"output\">{}</div>",
+                render_markdown(&read_text(markdown))
+            ));
+        }
+        data.get("text/plain")
+            .map(|text| self.text_output(&read_text(text)))
+    }
+
+    fn outputs(&self, outputs: &[Value]) -> Vec<String> {
+        outputs
+            .iter()
+            .filter_map(|output| match output["output_type"].as_str() {
+                Some("stream") => Some(self.text_output(&read_text(&output["text"]))),
+                Some("execute_result") | Some("display_data") => self.rich_output(&output["data"]),
+                Some("error") => Some(self.text_output(&read_text(&output["traceback"]))),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn process_notebook(notebook: &Value) -> (Content, Metadata) {
+    let mut metadata = read_metadata(notebook);
+    let converter = Converter {
+        language: notebook["metadata"]["language_info"]["name"]
+            .as_str()
+            .or_else(|| notebook["metadata"]["kernelspec"]["language"].as_str())
+            .unwrap_or("python")
+            .into(),
+        ansi: Regex::new(r"\x1B\[[0-9;]*[A-Za-z]").unwrap(),
+    };
+
+    let empty = Vec::new();
+    let cells = notebook["cells"].as_array().unwrap_or(&empty);
+    let mut html = Vec::new();
+
+    for (index, cell) in cells.iter().enumerate() {
+        let source = read_text(&cell["source"]);
+        match cell["cell_type"].as_str() {
+            Some("markdown") if index == 0 && !metadata.contains_key("title") => {
+                let (content, cell_metadata) = process_markdown(&source);
+                for (key, value) in cell_metadata {
+                    metadata.entry(key).or_insert(value);
+                }
+                html.push(content.html);
+            }
+            Some("markdown") => html.push(render_markdown(&source)),
+            Some("code") if !source.trim().is_empty() => {
+                html.push(converter.code(&source));
+                let outputs = cell["outputs"].as_array().unwrap_or(&empty);
+                html.extend(converter.outputs(outputs));
+            }
+            Some("raw") => html.push(source),
+            _ => (),
+        }
+    }
+
+    let html = html
+        .iter()
+        .map(|cell| cell.trim())
+        .filter(|cell| !cell.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    (Content::from_html(html), metadata)
+}
+
+#[test]
+fn test_process_notebook() {
+    let notebook = serde_json::json!({
+        "metadata": {
+            "authors": [{"name": "Jane"}, {"name": "John"}],
+            "tags": ["data", "python"],
+            "kernelspec": {"language": "python", "name": "python3"}
+        },
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Analysis\n", "\n", "Created: 2020-05-01\n", "\n", "Intro"]},
+            {"cell_type": "code", "source": "print(1 < 2)", "outputs": [
+                {"output_type": "stream", "name": "stdout", "text": ["True\n"]},
+                {"output_type": "display_data", "data": {
+                    "image/png": "iVBORw0K\nGgo=\n",
+                    "text/plain": ["<Figure>"]
+                }},
+                {"output_type": "error", "traceback": ["\u{1b}[0;31mValueError\u{1b}[0m"]}
+            ]},
+            {"cell_type": "markdown", "source": "## Results"},
+            {"cell_type": "code", "source": "", "outputs": []}
+        ]
+    });
+
+    let (content, metadata) = process_notebook(&notebook);
+    assert_eq!(metadata.get("title"), Some(&"Analysis".into()));
+    assert_eq!(metadata.get("created"), Some(&"2020-05-01".into()));
+    assert_eq!(metadata.get("keywords"), Some(&"data, python".into()));
+    assert_eq!(metadata.get("authors"), Some(&"Jane, John".into()));
+    assert_eq!(
+        content.html,
+        "\
+<p>Intro</p>
+<pre><code class=\"language-python\">print(1 &lt; 2)</code></pre>
+<pre class=\"output\">True</pre>
+<img class=\"output\" src=\"data:image/png;base64,iVBORw0KGgo=\" alt=\"\" />
+<pre class=\"output\">ValueError</pre>
+<h2>Results</h2>"
+    );
+    assert_eq!(content.headings, ["Results"]);
+
+    let notebook = serde_json::json!({
+        "metadata": {"title": "From metadata"},
+        "cells": [{"cell_type": "markdown", "source": "# Heading"}]
+    });
+    let (content, metadata) = process_notebook(&notebook);
+    assert_eq!(metadata.get("title"), Some(&"From metadata".into()));
+    assert_eq!(content.html, "<h1>Heading</h1>");
+}
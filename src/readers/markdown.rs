@@ -14,13 +14,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::super::document::Summary;
 use super::super::{Error, Result, Settings};
-use super::{Metadata, Reader};
+use super::{Content, HtmlPatterns, Metadata, Reader};
 use pulldown_cmark::{html, Event, Options, Parser, Tag};
 use regex::Regex;
 use std::fs::File;
 use std::io::Read;
-use std::mem::replace;
+use std::mem::take;
 use std::path::Path;
 
 #[derive(Debug, Clone)]
@@ -37,7 +38,7 @@ impl Reader for MarkdownReader {
         EXTENSIONS
     }
 
-    fn load(&self, path: &Path) -> Result<(String, Metadata)> {
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)> {
         let mut input = String::new();
         File::open(path)
             .and_then(|mut fd| fd.read_to_string(&mut input))
@@ -93,10 +94,7 @@ impl<'a> Iterator for MetadataExtractor<'a> {
             }
         }
 
-        let event = match self.inner.next() {
-            Some(ev) => ev,
-            None => return None,
-        };
+        let event = self.inner.next()?;
 
         match self.state {
             BeforeTitle => match event {
@@ -145,18 +143,15 @@ impl<'a> Iterator for MetadataExtractor<'a> {
                     self.next()
                 }
                 Event::End(Tag::Paragraph) => {
-                    self.metadata.extend(
-                        replace(&mut self.buffer, Vec::new())
-                            .into_iter()
-                            .filter_map(|event| {
-                                if let Event::Text(text) = event {
-                                    let (key, value) = split_pair(&text);
-                                    Some((key.to_ascii_lowercase(), value))
-                                } else {
-                                    None
-                                }
-                            }),
-                    );
+                    self.metadata
+                        .extend(take(&mut self.buffer).into_iter().filter_map(|event| {
+                            if let Event::Text(text) = event {
+                                let (key, value) = split_pair(&text);
+                                Some((key.to_ascii_lowercase(), value))
+                            } else {
+                                None
+                            }
+                        }));
                     self.state = State::InsideBody;
                     self.next()
                 }
@@ -175,6 +170,68 @@ impl<'a> Iterator for MetadataExtractor<'a> {
     }
 }
 
+/// Collects the text and the headings of a document while it is converted to
+/// HTML.
+struct TextExtractor<I> {
+    inner: I,
+    more: &'static Regex,
+    text: String,
+    headings: Vec<String>,
+    heading: Option<String>,
+    /// The text preceding the `<!-- more -->` marker
+    summary: Option<String>,
+}
+
+impl<I> From<I> for TextExtractor<I> {
+    fn from(inner: I) -> TextExtractor<I> {
+        TextExtractor {
+            inner,
+            more: &HtmlPatterns::get().more,
+            text: String::new(),
+            headings: Vec::new(),
+            heading: None,
+            summary: None,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Event<'a>>> Iterator for TextExtractor<I> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.inner.next()?;
+
+        match event {
+            Event::Text(ref text) | Event::Code(ref text) => {
+                self.text.push_str(text);
+                if let Some(ref mut heading) = self.heading {
+                    heading.push_str(text);
+                }
+            }
+            Event::Html(ref html) if self.summary.is_none() && self.more.is_match(html) => {
+                self.summary = Some(self.text.clone());
+            }
+            Event::Start(Tag::Heading(_)) => self.heading = Some(String::new()),
+            Event::End(Tag::Heading(_)) => {
+                if let Some(heading) = self.heading.take() {
+                    self.headings.push(heading.trim().into());
+                }
+                self.text.push(' ');
+            }
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::End(Tag::Paragraph)
+            | Event::End(Tag::Item)
+            | Event::End(Tag::CodeBlock(_))
+            | Event::End(Tag::TableCell)
+            | Event::End(Tag::BlockQuote) => self.text.push(' '),
+            _ => (),
+        }
+
+        Some(event)
+    }
+}
+
 fn split_pair<S: AsRef<str>>(input: &S) -> (String, String) {
     let mut split = input.as_ref().splitn(2, ':');
     let key: &str = split.next().unwrap_or("");
@@ -190,21 +247,55 @@ fn get_event_text<'a>(event: &Event<'a>) -> String {
     }
 }
 
-fn process_markdown<S: AsRef<str>>(input: &S) -> (String, Metadata) {
-    let mut parser = MetadataExtractor::from(Parser::new_ext(
-        input.as_ref(),
-        Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES,
-    ));
-    let mut output = String::with_capacity(input.as_ref().len() * (3 / 2));
-    html::push_html(&mut output, &mut parser);
-    (output, parser.metadata)
+fn normalize_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// The Markdown extensions enabled in the documents
+fn parser_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES
+}
+
+/// Converts a Markdown fragment to HTML, without extracting its title and
+/// metadata.
+pub(super) fn render_markdown(input: &str) -> String {
+    let mut output = String::with_capacity(input.len() * 3 / 2);
+    html::push_html(&mut output, Parser::new_ext(input, parser_options()));
+    output
+}
+
+pub(super) fn process_markdown<S: AsRef<str>>(input: &S) -> (Content, Metadata) {
+    let mut parser = MetadataExtractor::from(Parser::new_ext(input.as_ref(), parser_options()));
+    let mut output = String::with_capacity(input.as_ref().len() * 3 / 2);
+    let mut extractor = TextExtractor::from(&mut parser);
+    html::push_html(&mut output, &mut extractor);
+
+    let more = extractor.more;
+    let summary = extractor.summary.take().map(|text| {
+        let end = more
+            .find(&output)
+            .map(|marker| marker.start())
+            .unwrap_or_else(|| output.len());
+        Summary {
+            html: output[..end].trim_end().into(),
+            text: normalize_spaces(&text),
+        }
+    });
+
+    let content = Content {
+        text: normalize_spaces(&extractor.text),
+        html: output,
+        headings: extractor.headings,
+        summary,
+    };
+    (content, parser.metadata)
 }
 
 #[test]
 fn extract_title() {
     let (output, metadata) = process_markdown(&"# Foo\nbar\nbaz");
     assert_eq!(metadata.get("title"), Some(&"Foo".into()));
-    assert_eq!(output, "<p>bar\nbaz</p>\n");
+    assert_eq!(output.html, "<p>bar\nbaz</p>\n");
 }
 
 #[test]
@@ -214,7 +305,7 @@ fn extract_metadata() {
     assert_eq!(metadata.get("title"), Some(&"Foo".into()));
     assert_eq!(metadata.get("bar"), Some(&"baz:quux".into()));
     assert_eq!(metadata.get("foo bar"), Some(&"qux baz".into()));
-    assert_eq!(output, "<p>foo: bar</p>\n");
+    assert_eq!(output.html, "<p>foo: bar</p>\n");
 }
 
 #[test]
@@ -223,7 +314,7 @@ fn can_skip_to_metadata() {
     assert_eq!(metadata.get("title"), None);
     assert_eq!(metadata.get("bar"), Some(&"baz:quux".into()));
     assert_eq!(metadata.get("foo bar"), Some(&"qux baz".into()));
-    assert_eq!(output, "<p>foo: bar</p>\n");
+    assert_eq!(output.html, "<p>foo: bar</p>\n");
 }
 
 #[test]
@@ -234,7 +325,7 @@ fn can_skip_to_body() {
     assert_eq!(metadata.get("bar"), None);
     assert_eq!(metadata.get("foo bar"), None);
     assert_eq!(
-        output,
+        output.html,
         "<p>Bar: baz:quux\nFoo bar: qux baz<br />\nlol</p>\n<p>foo: bar</p>\n"
     );
 }
@@ -247,7 +338,29 @@ fn can_skip_metadata() {
     assert_eq!(metadata.get("bar"), None);
     assert_eq!(metadata.get("foo bar"), None);
     assert_eq!(
-        output,
+        output.html,
         "<p>Bar: baz:quux\nFoo bar: qux baz<br />\nlol</p>\n<p>foo: bar</p>\n"
     );
 }
+
+#[test]
+fn extract_text() {
+    let (output, _) = process_markdown(
+        &"# Title\n\nFoo: bar\n\nSome *text*\nwith `code`.\n\n## Sub *title*\n\n- one\n- two",
+    );
+    assert_eq!(output.text, "Some text with code. Sub title one two");
+    assert_eq!(output.headings, ["Sub title"]);
+}
+
+#[test]
+fn extract_summary() {
+    let (output, _) =
+        process_markdown(&"# Title\n\nFirst *paragraph*.\n\n<!-- more -->\n\nSecond.");
+    let summary = output.summary.unwrap();
+    assert_eq!(summary.html, "<p>First <em>paragraph</em>.</p>");
+    assert_eq!(summary.text, "First paragraph.");
+    assert_eq!(output.text, "First paragraph. Second.");
+
+    let (output, _) = process_markdown(&"First.\n\n    <!-- more -->\n");
+    assert_eq!(output.summary, None);
+}
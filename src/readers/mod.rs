@@ -14,21 +14,167 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::Result;
+use super::document::Summary;
+use super::{Error, Result};
+use regex::Regex;
 use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
+use std::sync::OnceLock;
 
+mod command;
+mod html;
+mod ipynb;
 mod markdown;
+mod org;
+mod rst;
+pub use self::command::{CommandError, CommandReader};
+pub use self::html::HtmlReader;
+pub use self::ipynb::NotebookReader;
 pub use self::markdown::MarkdownReader;
+pub use self::org::OrgReader;
+pub use self::rst::RstReader;
 
 pub type Metadata = HashMap<String, String>;
 
-pub trait Reader {
+/// The body of a document, as returned by a reader.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Content {
+    /// The body converted to HTML
+    pub html: String,
+    /// The text of the body, without markup
+    pub text: String,
+    /// The text of the headings of the body
+    pub headings: Vec<String>,
+    /// The beginning of the body, when the document marks the end of its
+    /// summary
+    pub summary: Option<Summary>,
+}
+
+/// The patterns used to extract the text of an HTML body.
+struct HtmlPatterns {
+    blocks: Regex,
+    tags: Regex,
+    headings: Regex,
+    more: Regex,
+}
+
+static HTML_PATTERNS: OnceLock<HtmlPatterns> = OnceLock::new();
+
+impl HtmlPatterns {
+    fn get() -> &'static HtmlPatterns {
+        HTML_PATTERNS.get_or_init(|| HtmlPatterns {
+            blocks: Regex::new(
+                r"(?i)</?(p|h[1-6]|ul|ol|li|dl|dt|dd|pre|blockquote|div|table|tr|td|th|br|hr)\b[^>]*>",
+            )
+            .unwrap(),
+            tags: Regex::new(r"(?s)<[^>]*>").unwrap(),
+            headings: Regex::new(r"(?is)<h[1-6][^>]*>(.*?)</h[1-6]>").unwrap(),
+            more: Regex::new(r"<!--\s*more\s*-->").unwrap(),
+        })
+    }
+}
+
+impl Content {
+    /// Builds the content of a document from its HTML body, extracting the
+    /// text, the headings and the part preceding a `<!-- more -->` marker.
+    pub fn from_html(html: String) -> Content {
+        let HtmlPatterns {
+            blocks,
+            tags,
+            headings,
+            more,
+        } = HtmlPatterns::get();
+        let to_text = |html: &str| {
+            unescape_html(&tags.replace_all(&blocks.replace_all(html, " "), ""))
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+        };
+
+        let summary = more.find(&html).map(|marker| {
+            let html = html[..marker.start()].trim_end();
+            Summary {
+                html: html.into(),
+                text: to_text(html),
+            }
+        });
+
+        Content {
+            text: to_text(&html),
+            headings: headings
+                .captures_iter(&html)
+                .map(|captures| to_text(&captures[1]))
+                .collect(),
+            html,
+            summary,
+        }
+    }
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&#x3D;", "=")
+        .replace("&#x60;", "`")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Escapes a text so that it can be written in HTML.
+fn escape_html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    pulldown_cmark::escape::escape_html(&mut output, text).unwrap();
+    output
+}
+
+/// Escapes an URL so that it can be written in an HTML attribute.
+fn escape_href(url: &str) -> String {
+    let mut output = String::with_capacity(url.len());
+    pulldown_cmark::escape::escape_href(&mut output, url).unwrap();
+    output
+}
+
+/// Reads the content of a document.
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).map_err(|err| Error::Reader {
+        path: path.into(),
+        cause: Box::new(err),
+    })
+}
+
+pub trait Reader: Send + Sync {
     fn extensions() -> &'static [&'static str]
     where
         Self: Sized;
     fn new(settings: &super::Settings) -> Self
     where
         Self: Sized;
-    fn load(&self, path: &Path) -> Result<(String, Metadata)>;
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)>;
+
+    /// Checks whether a file is a document this reader can load. The other
+    /// files with the same extensions are copied as is.
+    fn accepts(&self, _path: &Path) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_content_from_html() {
+    let content = Content::from_html(
+        "<h2 id=\"a\">Fish &amp; <em>chips</em></h2>\n<p>Intro</p>\n<!-- more -->\n<p>Rest</p>"
+            .into(),
+    );
+    assert_eq!(content.headings, ["Fish & chips"]);
+    assert_eq!(content.text, "Fish & chips Intro Rest");
+    assert_eq!(
+        content.summary,
+        Some(Summary {
+            html: "<h2 id=\"a\">Fish &amp; <em>chips</em></h2>\n<p>Intro</p>".into(),
+            text: "Fish & chips Intro".into(),
+        })
+    );
 }
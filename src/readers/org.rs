@@ -0,0 +1,336 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::{Result, Settings};
+use super::{escape_href, escape_html, read_file, Content, Metadata, Reader};
+use regex::Regex;
+use std::path::Path;
+
+/// Reads a subset of Org-mode: the `#+KEYWORD:` settings, headlines,
+/// paragraphs, lists, blocks and the usual inline markup.
+#[derive(Debug, Clone)]
+pub struct OrgReader;
+
+static EXTENSIONS: &[&str] = &["org"];
+
+impl Reader for OrgReader {
+    fn new(_settings: &Settings) -> OrgReader {
+        OrgReader
+    }
+
+    fn extensions() -> &'static [&'static str] {
+        EXTENSIONS
+    }
+
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)> {
+        Ok(process_org(&read_file(path)?))
+    }
+}
+
+/// Converts an Org date such as `<2020-05-01 Fri 14:00>` to a date understood
+/// by the metadata parser.
+fn read_date(value: &str) -> String {
+    value
+        .trim_matches(|c| c == '<' || c == '>' || c == '[' || c == ']')
+        .split_whitespace()
+        .filter(|part| !part.chars().all(char::is_alphabetic))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Converts the tags of `#+FILETAGS`, written as `:a:b:`, to a list of
+/// keywords.
+fn read_tags(value: &str) -> String {
+    value
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+struct Converter {
+    inline: Regex,
+    keyword: Regex,
+    headline: Regex,
+    item: Regex,
+    block: Regex,
+    metadata: Metadata,
+    html: Vec<String>,
+}
+
+impl Converter {
+    fn new() -> Converter {
+        Converter {
+            inline: Regex::new(concat!(
+                r"\[\[(?P<url>[^\]]+)\](?:\[(?P<description>[^\]]+)\])?\]",
+                r"|(?P<pre>^|[\s(\x22'])(?:",
+                r"=(?P<verbatim>[^=\s](?:[^=]*[^=\s])?)=",
+                r"|~(?P<code>[^~\s](?:[^~]*[^~\s])?)~",
+                r"|\*(?P<bold>[^*\s](?:[^*]*[^*\s])?)\*",
+                r"|/(?P<italic>[^/\s](?:[^/]*[^/\s])?)/",
+                r")",
+            ))
+            .unwrap(),
+            keyword: Regex::new(r"^#\+(\w+):\s*(.*)$").unwrap(),
+            headline: Regex::new(r"^(\*+)\s+(.*?)(\s+:[\w@#%:]+:)?\s*$").unwrap(),
+            item: Regex::new(r"^(\s*)([-+]|\s\*|\d+[.)])\s+").unwrap(),
+            block: Regex::new(r"(?i)^\s*#\+begin_(\w+)\s*(\S*)").unwrap(),
+            metadata: Metadata::new(),
+            html: Vec::new(),
+        }
+    }
+
+    fn render_inline(&self, text: &str) -> String {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let mut output = String::new();
+        let mut last = 0;
+
+        for captures in self.inline.captures_iter(&text) {
+            let whole = captures.get(0).unwrap();
+            output.push_str(&escape_html(&text[last..whole.start()]));
+            last = whole.end();
+
+            let group = |name: &str| captures.name(name).map(|m| escape_html(m.as_str()));
+            output.push_str(&group("pre").unwrap_or_default());
+            if let Some(url) = captures.name("url") {
+                output.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_href(url.as_str()),
+                    group("description").unwrap_or_else(|| escape_html(url.as_str()))
+                ));
+            } else if let Some(code) = group("verbatim").or_else(|| group("code")) {
+                output.push_str(&format!("<code>{}</code>", code));
+            } else if let Some(bold) = group("bold") {
+                output.push_str(&format!("<strong>{}</strong>", bold));
+            } else if let Some(italic) = group("italic") {
+                output.push_str(&format!("<em>{}</em>", italic));
+            }
+        }
+
+        output.push_str(&escape_html(&text[last..]));
+        output
+    }
+
+    fn read_keyword(&mut self, name: &str, value: &str) {
+        let name = name.to_lowercase();
+        let (key, value) = match name.as_str() {
+            "date" => ("created", read_date(value)),
+            "modified" => ("modified", read_date(value)),
+            "filetags" => ("keywords", read_tags(value)),
+            "description" => ("summary", value.into()),
+            name => (name, value.into()),
+        };
+        self.metadata.insert(key.into(), value);
+    }
+
+    /// Converts a `#+BEGIN_…` block and returns the number of lines it spans.
+    fn block(&mut self, lines: &[&str], kind: &str, language: &str) -> usize {
+        let end_marker = format!("#+end_{}", kind);
+        let length = lines[1..]
+            .iter()
+            .position(|line| line.trim().to_lowercase() == end_marker)
+            .unwrap_or(lines.len() - 1);
+        let body = &lines[1..1 + length];
+
+        match kind {
+            "quote" => {
+                let mut quote = Converter::new();
+                quote.convert(body);
+                self.html.push(format!(
+                    "<blockquote>\n{}\n</blockquote>",
+                    quote.html.join("\n")
+                ));
+            }
+            _ => {
+                let class = if kind == "src" && !language.is_empty() {
+                    format!(" class=\"language-{}\"", escape_html(language))
+                } else {
+                    String::new()
+                };
+                self.html.push(format!(
+                    "<pre><code{}>{}</code></pre>",
+                    class,
+                    escape_html(body.join("\n").trim_matches('\n'))
+                ));
+            }
+        }
+        length + 2
+    }
+
+    fn list(&mut self, lines: &[&str]) -> usize {
+        let ordered = self
+            .item
+            .captures(lines[0])
+            .is_some_and(|captures| captures[2].ends_with(['.', ')']));
+        let mut items: Vec<String> = Vec::new();
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            if let Some(found) = self.item.find(line) {
+                items.push(line[found.end()..].into());
+            } else if line.starts_with(char::is_whitespace) && !is_blank(line) {
+                let item = items.last_mut().unwrap();
+                item.push(' ');
+                item.push_str(line.trim());
+            } else {
+                break;
+            }
+            index += 1;
+        }
+
+        let tag = if ordered { "ol" } else { "ul" };
+        let items: Vec<String> = items
+            .iter()
+            .map(|item| format!("<li>{}</li>", self.render_inline(item)))
+            .collect();
+        self.html
+            .push(format!("<{}>\n{}\n</{}>", tag, items.join("\n"), tag));
+        index
+    }
+
+    fn convert(&mut self, lines: &[&str]) {
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            let rest = &lines[index..];
+
+            if is_blank(line) {
+                index += 1;
+            } else if let Some(captures) = self.block.captures(line) {
+                let kind = captures[1].to_lowercase();
+                let language = captures[2].to_string();
+                index += self.block(rest, &kind, &language);
+            } else if let Some(captures) = self.keyword.captures(line) {
+                let (name, value) = (captures[1].to_string(), captures[2].trim().to_string());
+                self.read_keyword(&name, &value);
+                index += 1;
+            } else if line.trim_start().starts_with('#') {
+                // Comments and unknown settings
+                if line.trim_start_matches('#').trim() == "more" {
+                    self.html.push("<!-- more -->".into());
+                }
+                index += 1;
+            } else if let Some(captures) = self.headline.captures(line) {
+                let level = (captures[1].len() + 1).min(6);
+                self.html.push(format!(
+                    "<h{}>{}</h{}>",
+                    level,
+                    self.render_inline(&captures[2]),
+                    level
+                ));
+                index += 1;
+            } else if line.trim().len() >= 5 && line.trim().chars().all(|c| c == '-') {
+                self.html.push("<hr />".into());
+                index += 1;
+            } else if self.item.is_match(line) {
+                index += self.list(rest);
+            } else {
+                let end = rest
+                    .iter()
+                    .position(|line| {
+                        is_blank(line)
+                            || self.block.is_match(line)
+                            || self.headline.is_match(line)
+                            || line.trim_start().starts_with('#')
+                    })
+                    .unwrap_or(rest.len());
+                let text = rest[..end].join("\n");
+                self.html
+                    .push(format!("<p>{}</p>", self.render_inline(&text)));
+                index += end;
+            }
+        }
+    }
+}
+
+fn process_org(input: &str) -> (Content, Metadata) {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut converter = Converter::new();
+    converter.convert(&lines);
+    let html = converter.html.join("\n");
+    (Content::from_html(html), converter.metadata)
+}
+
+#[test]
+fn test_read_date() {
+    assert_eq!(read_date("<2020-05-01 Fri>"), "2020-05-01");
+    assert_eq!(read_date("[2020-05-01 Fri 14:00]"), "2020-05-01 14:00");
+    assert_eq!(read_tags(":rust:web:"), "rust, web");
+}
+
+#[test]
+fn test_process_org() {
+    let input = "\
+#+TITLE: Title
+#+DATE: <2020-05-01 Fri>
+#+FILETAGS: :rust:web:
+#+AUTHOR: Jane
+
+Some /emphasis/, *bold*, =verbatim= and ~code~ with a [[http://example.com][link]]
+and [[http://example.org]], but not a/b/c.
+
+# A comment
+* Section
+- first item
+- second
+  item
+# more
+#+BEGIN_SRC rust
+fn main() { 1 < 2; }
+#+END_SRC
+** Subsection :tag:
+1. one
+2. two
+
+#+begin_quote
+A quote.
+#+end_quote
+";
+    let (content, metadata) = process_org(input);
+    assert_eq!(metadata.get("title"), Some(&"Title".into()));
+    assert_eq!(metadata.get("created"), Some(&"2020-05-01".into()));
+    assert_eq!(metadata.get("keywords"), Some(&"rust, web".into()));
+    assert_eq!(metadata.get("author"), Some(&"Jane".into()));
+    assert_eq!(
+        content.html,
+        "\
+<p>Some <em>emphasis</em>, <strong>bold</strong>, <code>verbatim</code> and <code>code</code> \
+with a <a href=\"http://example.com\">link</a> and \
+<a href=\"http://example.org\">http://example.org</a>, but not a/b/c.</p>
+<h2>Section</h2>
+<ul>
+<li>first item</li>
+<li>second item</li>
+</ul>
+<!-- more -->
+<pre><code class=\"language-rust\">fn main() { 1 &lt; 2; }</code></pre>
+<h3>Subsection</h3>
+<ol>
+<li>one</li>
+<li>two</li>
+</ol>
+<blockquote>
+<p>A quote.</p>
+</blockquote>"
+    );
+    assert_eq!(content.headings, ["Section", "Subsection"]);
+}
@@ -0,0 +1,451 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::super::{Result, Settings};
+use super::{escape_href, escape_html, read_file, Content, Metadata, Reader};
+use regex::Regex;
+use std::path::Path;
+
+/// Reads a subset of reStructuredText: section titles, field lists,
+/// paragraphs, lists, literal and code blocks, block quotes and the usual
+/// inline markup.
+#[derive(Debug, Clone)]
+pub struct RstReader;
+
+static EXTENSIONS: &[&str] = &["rst", "rest"];
+
+/// The characters which can adorn a section title
+const ADORNMENTS: &str = "=-`:'\"~^_*+#<>.";
+
+impl Reader for RstReader {
+    fn new(_settings: &Settings) -> RstReader {
+        RstReader
+    }
+
+    fn extensions() -> &'static [&'static str] {
+        EXTENSIONS
+    }
+
+    fn load(&self, path: &Path) -> Result<(Content, Metadata)> {
+        Ok(process_rst(&read_file(path)?))
+    }
+}
+
+/// Returns the character of a line made of a single repeated punctuation
+/// character.
+fn adornment(line: &str) -> Option<char> {
+    let line = line.trim_end();
+    let first = line.chars().next()?;
+    if line.chars().count() >= 2 && ADORNMENTS.contains(first) && line.chars().all(|c| c == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn is_indented(line: &str) -> bool {
+    line.starts_with(' ') || line.starts_with('\t')
+}
+
+/// Removes the common indentation of a block of lines.
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim_matches('\n')
+        .into()
+}
+
+struct Converter {
+    inline: Regex,
+    bullet: Regex,
+    enumerated: Regex,
+    field: Regex,
+    directive: Regex,
+    /// The adornment styles of the section titles, in order of appearance
+    styles: Vec<(char, bool)>,
+    metadata: Metadata,
+    html: Vec<String>,
+    /// Whether the converted lines are part of another block, and thus
+    /// cannot hold the title and the fields of the document
+    nested: bool,
+}
+
+impl Converter {
+    fn new() -> Converter {
+        Converter {
+            inline: Regex::new(concat!(
+                r"``(?P<literal>.+?)``",
+                r"|`(?P<text>[^`<]+?)\s*<(?P<url>[^>`]+)>`__?",
+                r"|:[\w-]+:`(?P<role>[^`]+)`",
+                r"|\*\*(?P<strong>[^*]+)\*\*",
+                r"|\*(?P<em>[^*\s][^*]*)\*",
+                r"|`(?P<interpreted>[^`]+)`",
+            ))
+            .unwrap(),
+            bullet: Regex::new(r"^[-*+]\s+").unwrap(),
+            enumerated: Regex::new(r"^(\d+|#|[a-zA-Z])[.)]\s+").unwrap(),
+            field: Regex::new(r"^:([^:]+):\s*(.*)$").unwrap(),
+            directive: Regex::new(r"^\.\.\s+(code-block|code|sourcecode)::\s*(\S*)").unwrap(),
+            styles: Vec::new(),
+            metadata: Metadata::new(),
+            html: Vec::new(),
+            nested: false,
+        }
+    }
+
+    fn render_inline(&self, text: &str) -> String {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let mut output = String::new();
+        let mut last = 0;
+
+        for captures in self.inline.captures_iter(&text) {
+            let whole = captures.get(0).unwrap();
+            output.push_str(&escape_html(&text[last..whole.start()]));
+            last = whole.end();
+
+            let group = |name: &str| captures.name(name).map(|m| escape_html(m.as_str()));
+            if let Some(literal) = group("literal") {
+                output.push_str(&format!("<code>{}</code>", literal));
+            } else if let Some(url) = captures.name("url") {
+                output.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_href(url.as_str()),
+                    group("text").unwrap_or_default()
+                ));
+            } else if let Some(strong) = group("strong") {
+                output.push_str(&format!("<strong>{}</strong>", strong));
+            } else if let Some(text) = group("em").or_else(|| group("interpreted")) {
+                output.push_str(&format!("<em>{}</em>", text));
+            } else if let Some(text) = group("role") {
+                output.push_str(&text);
+            }
+        }
+
+        output.push_str(&escape_html(&text[last..]));
+        output
+    }
+
+    /// Returns the text of a section title starting at `lines[0]`, with its
+    /// style and the number of lines it spans.
+    fn section_title(lines: &[&str]) -> Option<(String, (char, bool), usize)> {
+        match lines {
+            [over, text, under, ..] if adornment(over).is_some() && !is_blank(text) => {
+                let c = adornment(over)?;
+                if adornment(under) == Some(c) {
+                    Some((text.trim().into(), (c, true), 3))
+                } else {
+                    None
+                }
+            }
+            [text, under, ..] if !is_blank(text) && !is_indented(text) => {
+                let c = adornment(under)?;
+                if under.trim_end().chars().count() >= text.trim_end().chars().count() {
+                    Some((text.trim().into(), (c, false), 2))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads the field list following the title of the document.
+    fn read_fields(&mut self, lines: &[&str]) -> usize {
+        let mut index = 0;
+        while index < lines.len() && is_blank(lines[index]) {
+            index += 1;
+        }
+
+        let mut consumed = 0;
+        while let Some(captures) = lines.get(index).and_then(|line| self.field.captures(line)) {
+            let name = captures[1].trim().to_lowercase();
+            let value = captures[2].trim().to_string();
+            let key = match name.as_str() {
+                "date" | "created" => "created",
+                "modified" | "updated" => "modified",
+                "tags" | "keywords" => "keywords",
+                "lang" | "language" => "language",
+                "summary" | "abstract" => "summary",
+                name => name,
+            };
+            self.metadata.insert(key.into(), value);
+            index += 1;
+            consumed = index;
+        }
+        consumed
+    }
+
+    /// Returns the indented lines starting at `lines[0]`, including the blank
+    /// lines between them.
+    fn indented_block<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+        let mut end = 0;
+        for (index, line) in lines.iter().enumerate() {
+            if is_indented(line) && !is_blank(line) {
+                end = index + 1;
+            } else if !is_blank(line) {
+                break;
+            }
+        }
+        lines[..end].to_vec()
+    }
+
+    fn literal_block(&mut self, lines: &[&str], language: &str) -> usize {
+        let mut start = 0;
+        while start < lines.len() && is_blank(lines[start]) {
+            start += 1;
+        }
+        let block = Converter::indented_block(&lines[start..]);
+        let code: Vec<&str> = block
+            .iter()
+            .cloned()
+            .skip_while(|line| line.trim_start().starts_with(':'))
+            .collect();
+
+        let class = if language.is_empty() {
+            String::new()
+        } else {
+            format!(" class=\"language-{}\"", escape_html(language))
+        };
+        self.html.push(format!(
+            "<pre><code{}>{}</code></pre>",
+            class,
+            escape_html(&dedent(&code))
+        ));
+        start + block.len()
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        if !text.is_empty() {
+            let html = format!("<p>{}</p>", self.render_inline(text));
+            self.html.push(html);
+        }
+    }
+
+    fn list(&mut self, lines: &[&str], ordered: bool) -> usize {
+        let (marker, tag) = if ordered {
+            (&self.enumerated, "ol")
+        } else {
+            (&self.bullet, "ul")
+        };
+        let mut items: Vec<String> = Vec::new();
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            if let Some(found) = marker.find(line) {
+                items.push(line[found.end()..].into());
+            } else if is_indented(line) && !is_blank(lines[index - 1]) {
+                let item = items.last_mut().unwrap();
+                item.push(' ');
+                item.push_str(line.trim());
+            } else if !(is_blank(line)
+                && lines
+                    .get(index + 1)
+                    .is_some_and(|next| marker.is_match(next)))
+            {
+                break;
+            }
+            index += 1;
+        }
+
+        let items: Vec<String> = items
+            .iter()
+            .map(|item| format!("<li>{}</li>", self.render_inline(item)))
+            .collect();
+        self.html
+            .push(format!("<{}>\n{}\n</{}>", tag, items.join("\n"), tag));
+        index
+    }
+
+    /// Whether no part of the body has been converted yet.
+    fn at_start(&self) -> bool {
+        self.html.is_empty() && !self.nested
+    }
+
+    fn convert(&mut self, lines: &[&str]) {
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            let rest = &lines[index..];
+
+            if is_blank(line) {
+                index += 1;
+            } else if let Some((title, style, length)) = Converter::section_title(rest) {
+                let level = match self.styles.iter().position(|s| *s == style) {
+                    Some(level) => level,
+                    None => {
+                        self.styles.push(style);
+                        self.styles.len() - 1
+                    }
+                };
+                index += length;
+                if level == 0 && !self.metadata.contains_key("title") && self.at_start() {
+                    self.metadata.insert("title".into(), title);
+                    index += self.read_fields(&lines[index..]);
+                } else {
+                    let level = (level + 1).min(6);
+                    self.html.push(format!(
+                        "<h{}>{}</h{}>",
+                        level,
+                        self.render_inline(&title),
+                        level
+                    ));
+                }
+            } else if adornment(line).is_some() && line.trim().len() >= 4 {
+                self.html.push("<hr />".into());
+                index += 1;
+            } else if self.at_start() && self.field.is_match(line) {
+                index += self.read_fields(rest).max(1);
+            } else if let Some(captures) = self.directive.captures(line) {
+                let language = captures[2].to_string();
+                index += 1 + self.literal_block(&lines[index + 1..], &language);
+            } else if line.starts_with("..") {
+                if line.trim_start_matches('.').trim() == "more" {
+                    self.html.push("<!-- more -->".into());
+                }
+                index += 1 + Converter::indented_block(&lines[index + 1..]).len();
+            } else if self.bullet.is_match(line) {
+                index += self.list(rest, false);
+            } else if self.enumerated.is_match(line) {
+                index += self.list(rest, true);
+            } else if is_indented(line) {
+                let block = Converter::indented_block(rest);
+                index += block.len();
+                let mut quote = Converter::new();
+                quote.styles = self.styles.clone();
+                quote.nested = true;
+                quote.convert(&dedent(&block).lines().collect::<Vec<&str>>());
+                self.html.push(format!(
+                    "<blockquote>\n{}\n</blockquote>",
+                    quote.html.join("\n")
+                ));
+            } else {
+                let end = rest
+                    .iter()
+                    .position(|line| is_blank(line))
+                    .unwrap_or(rest.len());
+                let text = rest[..end].join("\n");
+                index += end;
+
+                let text = text.trim_end();
+                if let Some(text) = text.strip_suffix("::") {
+                    // `Paragraph::` is written as `Paragraph:`, while `Paragraph ::`
+                    // and a lone `::` drop the marker.
+                    if text.ends_with(char::is_whitespace) || text.is_empty() {
+                        self.paragraph(text.trim_end());
+                    } else {
+                        self.paragraph(&format!("{}:", text));
+                    }
+                    index += self.literal_block(&lines[index..], "");
+                } else {
+                    self.paragraph(text);
+                }
+            }
+        }
+    }
+}
+
+fn process_rst(input: &str) -> (Content, Metadata) {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut converter = Converter::new();
+    converter.convert(&lines);
+    let html = converter.html.join("\n");
+    (Content::from_html(html), converter.metadata)
+}
+
+#[test]
+fn test_process_rst() {
+    let input = "\
+=====
+Title
+=====
+
+:date: 2020-05-01
+:tags: rust, web
+:author: Jane
+
+Some *emphasis*, **strong** and ``code`` with a `link <http://example.com>`_.
+
+Section
+-------
+
+- first item
+- second
+  item
+
+.. more
+
+Example::
+
+    let x = 1 < 2;
+
+.. code-block:: rust
+
+    fn main() {}
+
+Subsection
+~~~~~~~~~~
+
+1. one
+2. two
+
+    A quote.
+";
+    let (content, metadata) = process_rst(input);
+    assert_eq!(metadata.get("title"), Some(&"Title".into()));
+    assert_eq!(metadata.get("created"), Some(&"2020-05-01".into()));
+    assert_eq!(metadata.get("keywords"), Some(&"rust, web".into()));
+    assert_eq!(metadata.get("author"), Some(&"Jane".into()));
+    assert_eq!(
+        content.html,
+        "\
+<p>Some <em>emphasis</em>, <strong>strong</strong> and <code>code</code> with a \
+<a href=\"http://example.com\">link</a>.</p>
+<h2>Section</h2>
+<ul>
+<li>first item</li>
+<li>second item</li>
+</ul>
+<!-- more -->
+<p>Example:</p>
+<pre><code>let x = 1 &lt; 2;</code></pre>
+<pre><code class=\"language-rust\">fn main() {}</code></pre>
+<h3>Subsection</h3>
+<ol>
+<li>one</li>
+<li>two</li>
+</ol>
+<blockquote>
+<p>A quote.</p>
+</blockquote>"
+    );
+    assert_eq!(content.headings, ["Section", "Subsection"]);
+    assert!(content.summary.is_some());
+}
@@ -0,0 +1,173 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::document::{Document, DocumentLink};
+use super::sections::section_of;
+use super::settings::RelatedSettings;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Returns a number between 0 and 1 measuring how close two directories are.
+fn directory_proximity(a: &str, b: &str) -> f64 {
+    let a: Vec<&str> = a.split('/').filter(|s| !s.is_empty()).collect();
+    let b: Vec<&str> = b.split('/').filter(|s| !s.is_empty()).collect();
+    let depth = a.len().max(b.len());
+
+    if depth == 0 {
+        return 1.0;
+    }
+
+    let common = a.iter().zip(b.iter()).take_while(|(a, b)| a == b).count();
+    common as f64 / depth as f64
+}
+
+/// Links each document to the documents written in the same language which
+/// share the most terms with it.
+///
+/// Only the documents sharing at least one term are considered; the proximity
+/// of their directories is then added to their score.
+pub fn link_related(documents: &mut [Document], settings: &RelatedSettings) {
+    if settings.count == 0 || settings.fields.is_empty() {
+        return;
+    }
+
+    // The documents of each (language, field, term)
+    let mut index: BTreeMap<(Option<String>, &str, String), Vec<usize>> = BTreeMap::new();
+    let mut terms: Vec<Vec<(&str, String)>> = Vec::with_capacity(documents.len());
+
+    for (position, document) in documents.iter().enumerate() {
+        let mut document_terms = BTreeSet::new();
+        for field in settings.fields.keys() {
+            for term in document.metadata.terms(field) {
+                document_terms.insert((field.as_str(), term.trim().to_lowercase()));
+            }
+        }
+
+        for (field, term) in document_terms.iter() {
+            index
+                .entry((document.metadata.language.clone(), field, term.clone()))
+                .or_default()
+                .push(position);
+        }
+        terms.push(document_terms.into_iter().collect());
+    }
+
+    let related: Vec<Vec<DocumentLink>> = documents
+        .iter()
+        .enumerate()
+        .map(|(position, document)| {
+            let mut scores: BTreeMap<usize, f64> = BTreeMap::new();
+            for (field, term) in terms[position].iter() {
+                let key = (document.metadata.language.clone(), *field, term.clone());
+                let weight = settings.fields[*field];
+                for &other in index[&key].iter().filter(|&&other| other != position) {
+                    *scores.entry(other).or_insert(0.0) += weight;
+                }
+            }
+
+            let section = section_of(&document.metadata.url);
+            let mut scores: Vec<(usize, f64)> = scores
+                .into_iter()
+                .filter(|&(_, score)| score > 0.0)
+                .map(|(other, score)| {
+                    let proximity =
+                        directory_proximity(section, section_of(&documents[other].metadata.url));
+                    (other, score + proximity * settings.directory_weight)
+                })
+                .collect();
+
+            scores.sort_by(|&(a, a_score), &(b, b_score)| {
+                b_score
+                    .partial_cmp(&a_score)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| {
+                        documents[b]
+                            .metadata
+                            .created
+                            .cmp(&documents[a].metadata.created)
+                    })
+                    .then_with(|| documents[a].metadata.url.cmp(&documents[b].metadata.url))
+            });
+
+            scores
+                .into_iter()
+                .take(settings.count)
+                .map(|(other, _)| DocumentLink::from(&documents[other].metadata))
+                .collect()
+        })
+        .collect();
+
+    for (document, related) in documents.iter_mut().zip(related) {
+        document.metadata.related = related;
+    }
+}
+
+#[test]
+fn test_directory_proximity() {
+    assert_eq!(directory_proximity("", ""), 1.0);
+    assert_eq!(directory_proximity("blog", "blog"), 1.0);
+    assert_eq!(directory_proximity("blog/2020", "blog/2021"), 0.5);
+    assert_eq!(directory_proximity("blog", "docs"), 0.0);
+    assert_eq!(directory_proximity("", "docs"), 0.0);
+}
+
+#[test]
+fn test_link_related() {
+    use super::{DocumentContent, DocumentMetadata};
+
+    let document = |url: &str, keywords: &[&str], tags: &str| {
+        let mut metadata = DocumentMetadata {
+            url: url.into(),
+            title: url.into(),
+            keywords: keywords.iter().map(|k| String::from(*k)).collect(),
+            ..DocumentMetadata::default()
+        };
+        metadata.extra.insert("tags".into(), tags.into());
+        Document::new(metadata, DocumentContent::from(String::new()))
+    };
+
+    let mut documents = vec![
+        document("blog/a.html", &["rust", "web"], ""),
+        document("blog/b.html", &["Rust"], ""),
+        document("docs/c.html", &["rust", "web"], ""),
+        document("docs/d.html", &["python"], "web"),
+        document("e.html", &[], ""),
+    ];
+
+    let mut settings = RelatedSettings::default();
+    settings.fields.insert("tags".into(), 0.5);
+    settings.count = 2;
+    link_related(&mut documents, &settings);
+
+    let urls = |document: &Document| -> Vec<String> {
+        document
+            .metadata
+            .related
+            .iter()
+            .map(|link| link.url.clone())
+            .collect()
+    };
+    // c shares two keywords, b shares one keyword but is in the same directory
+    assert_eq!(urls(&documents[0]), ["docs/c.html", "blog/b.html"]);
+    assert_eq!(urls(&documents[3]), Vec::<String>::new());
+    assert_eq!(urls(&documents[4]), Vec::<String>::new());
+
+    // Only the configured fields are compared
+    settings.fields.remove("keywords");
+    settings.fields.insert("tags".into(), 1.0);
+    link_related(&mut documents, &settings);
+    assert!(urls(&documents[0]).is_empty());
+}
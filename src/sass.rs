@@ -0,0 +1,145 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::settings::SassStyle;
+use super::{Error, Result, Settings};
+use grass::{Fs, Options, OutputStyle};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A compiled stylesheet.
+#[derive(Debug)]
+pub struct Stylesheet {
+    pub css: String,
+    /// The files imported by the stylesheet
+    pub imports: Vec<PathBuf>,
+}
+
+/// Reads the files from the disk, remembering which files were read.
+#[derive(Debug, Default)]
+struct RecordingFs {
+    read: Mutex<Vec<PathBuf>>,
+}
+
+impl Fs for RecordingFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.read.lock().unwrap().push(path.into());
+        fs::read(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+}
+
+/// Returns the path of the compiled stylesheet.
+pub fn output_path(relpath: &Path) -> PathBuf {
+    relpath.with_extension("css")
+}
+
+/// Compiles a Sass stylesheet. Its imports are searched in its directory and
+/// in the load paths of the settings.
+pub fn compile(path: &Path, settings: &Settings) -> Result<Stylesheet> {
+    let load_paths: Vec<PathBuf> = settings
+        .sass
+        .load_paths
+        .iter()
+        .map(|load_path| settings.source_dir.join(load_path))
+        .collect();
+    let options = Options::default()
+        .style(match settings.sass.style {
+            SassStyle::Expanded => OutputStyle::Expanded,
+            SassStyle::Compressed => OutputStyle::Compressed,
+        })
+        .load_paths(&load_paths);
+    let recording = RecordingFs::default();
+    let options = options.fs(&recording);
+
+    let css = grass::from_path(path, &options).map_err(|err| Error::Stylesheet {
+        path: path.into(),
+        message: err.to_string(),
+    })?;
+    let mut imports = recording.read.into_inner().unwrap();
+    imports.retain(|import| import != path);
+    imports.sort();
+    imports.dedup();
+    Ok(Stylesheet { css, imports })
+}
+
+#[test]
+fn test_compile() {
+    use crate::settings::SassSettings;
+
+    let dir = crate::utils::TempDir::new("sass");
+    fs::create_dir_all(dir.join("vendor")).unwrap();
+    fs::write(dir.join("_colors.scss"), "$main: red;\n").unwrap();
+    fs::write(dir.join("vendor/_reset.scss"), "p { margin: 0 }\n").unwrap();
+    fs::write(
+        dir.join("main.scss"),
+        "@use \"colors\";\n@use \"reset\";\na { color: colors.$main; }\n",
+    )
+    .unwrap();
+    fs::write(dir.join("broken.scss"), "a {\n  color: $missing;\n}\n").unwrap();
+
+    let settings = Settings {
+        source_dir: dir.to_path_buf(),
+        sass: SassSettings {
+            enabled: true,
+            style: SassStyle::Compressed,
+            load_paths: vec!["vendor".into()],
+        },
+        ..Settings::default()
+    };
+
+    let stylesheet = compile(&dir.join("main.scss"), &settings).unwrap();
+    let err = compile(&dir.join("broken.scss"), &settings).unwrap_err();
+
+    assert_eq!(stylesheet.css, "p{margin:0}a{color:red}");
+    let mut imports: Vec<PathBuf> = stylesheet
+        .imports
+        .iter()
+        .map(|import| fs::canonicalize(import).unwrap())
+        .collect();
+    imports.sort();
+    let mut expected = vec![
+        fs::canonicalize(dir.join("_colors.scss")).unwrap(),
+        fs::canonicalize(dir.join("vendor/_reset.scss")).unwrap(),
+    ];
+    expected.sort();
+    assert_eq!(imports, expected);
+
+    let message = err.to_string();
+    assert!(message.contains("Undefined variable"), "{}", message);
+    assert!(message.contains("broken.scss:2:10"), "{}", message);
+}
+
+#[test]
+fn test_output_path() {
+    assert_eq!(
+        output_path(Path::new("css/main.scss")),
+        Path::new("css/main.css")
+    );
+}
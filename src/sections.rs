@@ -0,0 +1,286 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::document::{Breadcrumb, Document, DocumentLink, DocumentMetadata};
+use super::settings::{SectionSettings, SortKey, SortOrder};
+use super::Settings;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Returns the section of a document, i.e. the directory containing it.
+pub fn section_of(url: &str) -> &str {
+    Path::new(url)
+        .parent()
+        .and_then(Path::to_str)
+        .unwrap_or("")
+        .trim_matches('/')
+}
+
+fn compare(a: &DocumentMetadata, b: &DocumentMetadata, section: SectionSettings) -> Ordering {
+    let ordering = match section.sort_by {
+        SortKey::Created => a.created.cmp(&b.created),
+        SortKey::Modified => a.modified.cmp(&b.modified),
+        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        SortKey::Url => a.url.cmp(&b.url),
+    };
+
+    match section.order {
+        SortOrder::Ascending => ordering,
+        SortOrder::Descending => ordering.reverse(),
+    }
+}
+
+/// Sorts the documents of a section in the configured order.
+pub fn sort<D: Borrow<DocumentMetadata>>(documents: &mut [D], section: SectionSettings) {
+    documents.sort_by(|a, b| compare(a.borrow(), b.borrow(), section));
+}
+
+/// Links each document to the previous and next documents written in the
+/// same language in its section.
+pub fn link_neighbours(documents: &mut [Document], settings: &Settings) {
+    let mut sections: BTreeMap<(String, Option<String>), Vec<usize>> = BTreeMap::new();
+
+    for (index, document) in documents.iter().enumerate() {
+        let key = (
+            section_of(&document.metadata.url).into(),
+            document.metadata.language.clone(),
+        );
+        sections.entry(key).or_default().push(index);
+    }
+
+    for ((section, _), mut indexes) in sections {
+        let section = settings.section(&section);
+        indexes.sort_by(|&a, &b| compare(&documents[a].metadata, &documents[b].metadata, section));
+
+        for (position, &index) in indexes.iter().enumerate() {
+            let previous = position
+                .checked_sub(1)
+                .map(|p| DocumentLink::from(&documents[indexes[p]].metadata));
+            let next = indexes
+                .get(position + 1)
+                .map(|&n| DocumentLink::from(&documents[n].metadata));
+
+            let metadata = &mut documents[index].metadata;
+            metadata.previous = previous;
+            metadata.next = next;
+        }
+    }
+}
+
+/// Returns the URL of the index of a directory, written in `language`, or in
+/// the default language when `language` is `None`.
+///
+/// Indexes are written `index.fr.html`, or `index.html` inside a language
+/// directory such as `fr/`.
+pub fn index_url(dir: &str, language: Option<&str>) -> String {
+    let dir = dir.trim_matches('/');
+    let prefix = if dir.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", dir)
+    };
+
+    match language {
+        Some(code) if dir != code && !dir.starts_with(&format!("{}/", code)) => {
+            format!("{}index.{}.html", prefix, code)
+        }
+        _ => format!("{}index.html", prefix),
+    }
+}
+
+/// Returns the language of the indexes listing a document written in
+/// `language`, or `None` for the indexes of the default language.
+pub fn index_language(language: Option<&str>, settings: &Settings) -> Option<String> {
+    if !settings.languages.is_empty() && language != settings.site_language.as_deref() {
+        language.map(String::from)
+    } else {
+        None
+    }
+}
+
+/// Returns the title of a directory, as configured in `[sections.<dir>]`.
+pub fn section_title(dir: &str, settings: &Settings) -> String {
+    let dir = dir.trim_matches('/');
+    match settings.section_titles.get(dir) {
+        Some(title) => title.clone(),
+        None if dir.is_empty() => "Index of /".into(),
+        None => format!("Index of {}", dir),
+    }
+}
+
+/// Returns the indexes of the parent directories of the document at `url`,
+/// from the root of the website.
+///
+/// `titles` contains the titles of the documents of the website, so that an
+/// index written by the user takes precedence over the generated one.
+pub fn breadcrumbs(
+    url: &str,
+    language: Option<&str>,
+    titles: &HashMap<String, String>,
+    settings: &Settings,
+) -> Vec<Breadcrumb> {
+    let language = index_language(language, settings);
+    let url = url.trim_start_matches('/');
+    let mut breadcrumbs = Vec::new();
+    let mut path = Path::new(url);
+
+    while let Some(parent) = path.parent() {
+        let dir = parent.to_str().unwrap_or("");
+        let index = index_url(dir, language.as_deref());
+
+        if index != url {
+            let title = match titles.get(&index) {
+                Some(title) => title.clone(),
+                None => section_title(dir, settings),
+            };
+            breadcrumbs.push(Breadcrumb { title, url: index });
+        }
+
+        // Documents inside a language directory are not linked above it
+        if language.as_deref() == Some(dir) {
+            break;
+        }
+        path = parent;
+    }
+
+    breadcrumbs.reverse();
+    breadcrumbs
+}
+
+/// Links each document to the indexes of its parent directories.
+pub fn link_breadcrumbs(documents: &mut [Document], settings: &Settings) {
+    let titles: HashMap<String, String> = documents
+        .iter()
+        .map(|document| {
+            (
+                document.metadata.url.trim_start_matches('/').into(),
+                document.metadata.title.clone(),
+            )
+        })
+        .collect();
+
+    for document in documents.iter_mut() {
+        let metadata = &mut document.metadata;
+        metadata.breadcrumbs = breadcrumbs(
+            &metadata.url,
+            metadata.language.as_deref(),
+            &titles,
+            settings,
+        );
+    }
+}
+
+#[test]
+fn test_section_of() {
+    assert_eq!(section_of("blog/2020/post.html"), "blog/2020");
+    assert_eq!(section_of("/blog/index.html"), "blog");
+    assert_eq!(section_of("about.html"), "");
+}
+
+#[test]
+fn test_link_neighbours() {
+    use super::DocumentContent;
+    use crate::utils::DateTime;
+
+    let document = |url: &str, created: &str| {
+        Document::new(
+            DocumentMetadata {
+                url: url.into(),
+                title: url.into(),
+                created: DateTime::from_string(created),
+                ..DocumentMetadata::default()
+            },
+            DocumentContent::from(String::new()),
+        )
+    };
+
+    let mut documents = vec![
+        document("blog/a.html", "2020-01-01T00:00:00Z"),
+        document("blog/b.html", "2021-01-01T00:00:00Z"),
+        document("blog/c.html", "2019-01-01T00:00:00Z"),
+        document("about.html", "2020-06-01T00:00:00Z"),
+    ];
+    link_neighbours(&mut documents, &Settings::default());
+
+    let url = |link: &Option<DocumentLink>| link.as_ref().map(|l| l.url.clone());
+    assert_eq!(url(&documents[1].metadata.previous), None);
+    assert_eq!(url(&documents[1].metadata.next), Some("blog/a.html".into()));
+    assert_eq!(
+        url(&documents[0].metadata.previous),
+        Some("blog/b.html".into())
+    );
+    assert_eq!(url(&documents[0].metadata.next), Some("blog/c.html".into()));
+    assert_eq!(url(&documents[2].metadata.next), None);
+    assert_eq!(url(&documents[3].metadata.previous), None);
+    assert_eq!(url(&documents[3].metadata.next), None);
+}
+
+#[test]
+fn test_index_url() {
+    assert_eq!(index_url("", None), "index.html");
+    assert_eq!(index_url("blog", None), "blog/index.html");
+    assert_eq!(index_url("blog", Some("fr")), "blog/index.fr.html");
+    assert_eq!(index_url("fr/blog", Some("fr")), "fr/blog/index.html");
+    assert_eq!(index_url("fr", Some("fr")), "fr/index.html");
+}
+
+#[test]
+fn test_breadcrumbs() {
+    let mut settings = Settings::default();
+    settings.section_titles.insert("blog".into(), "Blog".into());
+    let mut titles = HashMap::new();
+    titles.insert("index.html".into(), "Home".into());
+
+    let trail = |url: &str, language: Option<&str>, settings: &Settings| -> Vec<(String, String)> {
+        breadcrumbs(url, language, &titles, settings)
+            .into_iter()
+            .map(|breadcrumb| (breadcrumb.title, breadcrumb.url))
+            .collect()
+    };
+
+    assert_eq!(
+        trail("blog/2020/post.html", None, &settings),
+        [
+            ("Home".into(), "index.html".into()),
+            ("Blog".into(), "blog/index.html".into()),
+            ("Index of blog/2020".into(), "blog/2020/index.html".into()),
+        ]
+    );
+    assert_eq!(
+        trail("blog/index.html", None, &settings),
+        [("Home".into(), "index.html".into())]
+    );
+    assert!(trail("index.html", None, &settings).is_empty());
+
+    settings.site_language = Some("en".into());
+    settings.languages.insert("fr".into(), Default::default());
+    assert_eq!(
+        trail("fr/blog/post.html", Some("fr"), &settings),
+        [
+            ("Index of fr".into(), "fr/index.html".into()),
+            ("Index of fr/blog".into(), "fr/blog/index.html".into()),
+        ]
+    );
+    assert_eq!(
+        trail("blog/post.fr.html", Some("fr"), &settings),
+        [
+            ("Index of /".into(), "index.fr.html".into()),
+            ("Blog".into(), "blog/index.fr.html".into()),
+        ]
+    );
+}
@@ -14,24 +14,338 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::utils::remove_leading_dot;
+use super::assets::content_hash;
+use super::collections::{self, Condition};
+use super::utils::{parse_utc_offset, remove_leading_dot};
 use super::{Error, Result};
+use chrono::FixedOffset;
 use num::NumCast;
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::result;
+use toml::value::Table;
 use toml::{self, Value};
 
+/// Where the missing creation and modification dates of the documents are read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatesSource {
+    /// The modification time of the source file
+    Filesystem,
+    /// The first and last commits touching the source file
+    Git,
+}
+
+/// The settings specific to one of the languages of a multilingual website.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LanguageSettings {
+    /// The name of the language, as displayed to the visitors
+    pub name: Option<String>,
+    /// The title of the website in this language
+    pub title: Option<String>,
+    /// The unit shown after the reading time in this language
+    pub reading_time_label: Option<String>,
+}
+
+/// The field used to sort the documents of a section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Created,
+    Modified,
+    Title,
+    Url,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// The settings of a directory of the website, and of its subdirectories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionSettings {
+    pub sort_by: SortKey,
+    pub order: SortOrder,
+    /// Whether the indexes include the content of the documents they list
+    pub full_content: bool,
+}
+
+impl Default for SectionSettings {
+    fn default() -> SectionSettings {
+        SectionSettings {
+            sort_by: SortKey::Created,
+            order: SortOrder::Descending,
+            full_content: false,
+        }
+    }
+}
+
+/// The settings of the related documents computation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelatedSettings {
+    /// The maximum number of related documents of each document
+    pub count: usize,
+    /// The list fields compared between the documents, and their weights
+    pub fields: BTreeMap<String, f64>,
+    /// The weight of the proximity between the directories of the documents
+    pub directory_weight: f64,
+}
+
+impl Default for RelatedSettings {
+    fn default() -> RelatedSettings {
+        let mut fields = BTreeMap::new();
+        fields.insert("keywords".into(), 1.0);
+        RelatedSettings {
+            count: 5,
+            fields,
+            directory_weight: 0.5,
+        }
+    }
+}
+
+/// An entry of a navigation menu declared in the settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MenuEntrySettings {
+    pub name: String,
+    pub url: String,
+    pub weight: i64,
+    /// The identifier used by the children of this entry (defaults to the name)
+    pub identifier: Option<String>,
+    /// The identifier of the parent of this entry
+    pub parent: Option<String>,
+    /// The language of the pages where this entry is displayed
+    pub language: Option<String>,
+}
+
+/// The settings of the search index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchSettings {
+    pub enabled: bool,
+    /// The path of the index, relative to the output directory
+    pub output: String,
+    /// The fields written for each document
+    pub fields: Vec<String>,
+    /// The documents and directories which are not indexed
+    pub exclude: Vec<String>,
+    /// The maximum number of characters of the content, or `0` to keep it all
+    pub content_length: usize,
+    /// Whether to write the documents containing each term
+    pub inverted_index: bool,
+}
+
+impl Default for SearchSettings {
+    fn default() -> SearchSettings {
+        SearchSettings {
+            enabled: false,
+            output: "search.json".into(),
+            fields: vec![
+                "title".into(),
+                "keywords".into(),
+                "headings".into(),
+                "content".into(),
+            ],
+            exclude: Vec::new(),
+            content_length: 0,
+            inverted_index: false,
+        }
+    }
+}
+
+/// A named list of documents, selected anywhere in the website.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollectionSettings {
+    /// The glob matched against the paths of the sources of the documents
+    pub pattern: String,
+    /// The conditions on the metadata of the documents, such as `draft != true`
+    pub filters: Vec<Condition>,
+    pub sort_by: SortKey,
+    pub order: SortOrder,
+    pub title: Option<String>,
+    /// The URL of the index of the collection, if any
+    pub index: Option<String>,
+}
+
+impl Default for CollectionSettings {
+    fn default() -> CollectionSettings {
+        CollectionSettings {
+            pattern: "**".into(),
+            filters: Vec::new(),
+            sort_by: SortKey::Created,
+            order: SortOrder::Descending,
+            title: None,
+            index: None,
+        }
+    }
+}
+
+/// The settings of the asset pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetSettings {
+    /// Whether the names of the assets include a hash of their content
+    pub fingerprint: bool,
+    /// The directories containing the assets, relative to the source directory
+    pub directories: Vec<String>,
+    /// The path of the JSON file mapping each asset to its fingerprinted path
+    pub manifest: String,
+    /// The number of hexadecimal digits of the hashes
+    pub hash_length: usize,
+    /// The files concatenated into each bundle, indexed by the path of the
+    /// bundle
+    pub bundles: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for AssetSettings {
+    fn default() -> AssetSettings {
+        AssetSettings {
+            fingerprint: false,
+            directories: vec!["css".into(), "js".into(), "img".into()],
+            manifest: "assets.json".into(),
+            hash_length: 8,
+            bundles: BTreeMap::new(),
+        }
+    }
+}
+
+/// The types of files which are minified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MinifySettings {
+    /// Minify the rendered pages
+    pub html: bool,
+    /// Minify the stylesheets
+    pub css: bool,
+    /// Minify the scripts
+    pub js: bool,
+}
+
+/// The settings of the image pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageSettings {
+    /// Whether the images are resized and converted
+    pub enabled: bool,
+    /// The widths of the resized variants, in pixels
+    pub widths: Vec<u32>,
+    /// The formats of the converted variants, such as `webp`
+    pub formats: Vec<String>,
+    /// The quality of the JPEG images, from 1 to 100
+    pub quality: u8,
+    /// The default `sizes` attribute of the images
+    pub sizes: String,
+}
+
+impl Default for ImageSettings {
+    fn default() -> ImageSettings {
+        ImageSettings {
+            enabled: false,
+            widths: vec![480, 960, 1920],
+            formats: vec!["webp".into()],
+            quality: 80,
+            sizes: "100vw".into(),
+        }
+    }
+}
+
+/// The formatting of the compiled stylesheets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SassStyle {
+    Expanded,
+    Compressed,
+}
+
+/// The settings of the Sass compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SassSettings {
+    /// Whether the `.scss` and `.sass` files are compiled to CSS
+    pub enabled: bool,
+    pub style: SassStyle,
+    /// The directories searched for imported files, relative to the source
+    /// directory
+    pub load_paths: Vec<PathBuf>,
+}
+
+impl Default for SassSettings {
+    fn default() -> SassSettings {
+        SassSettings {
+            enabled: true,
+            style: SassStyle::Expanded,
+            load_paths: Vec::new(),
+        }
+    }
+}
+
+/// How an external command receives the document it converts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandInput {
+    /// The path of the document is passed as an argument
+    Argument,
+    /// The content of the document is written to the standard input
+    Stdin,
+}
+
+/// A reader running an external command to convert some documents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandReaderSettings {
+    /// The program and its arguments. The `{path}` arguments are replaced by
+    /// the path of the document, which is otherwise appended to them.
+    pub command: Vec<String>,
+    /// The extensions of the documents converted by this command
+    pub extensions: Vec<String>,
+    pub input: CommandInput,
+}
+
 #[derive(Clone, Debug)]
 pub struct Settings {
     pub source_dir: PathBuf,
     pub output_dir: PathBuf,
     pub layouts_dir: PathBuf,
+    /// The directory containing the data files exposed to the templates
+    pub data_dir: PathBuf,
+    /// The directory keeping the caches between the builds, or `None` to use
+    /// a directory of the cache of the user
+    pub cache_dir: Option<PathBuf>,
     pub max_depth: usize,
     pub follow_links: bool,
+    /// The number of worker threads, or `0` to use one thread per CPU
+    pub jobs: usize,
+    /// The hidden files of the source directory which are read anyway
+    pub include: Vec<String>,
+    /// The files of the source directory which are ignored, in addition to
+    /// `helianto.toml`, `README.md` and `node_modules`
+    pub exclude: Vec<String>,
+    /// The files of the output directory which are never removed, in
+    /// addition to `.git` and `CNAME`
+    pub keep: Vec<String>,
+    /// Whether the output directory is emptied before the build
+    pub clean: bool,
+    pub dates_from: Option<DatesSource>,
     pub site_title: String,
     pub site_url: String,
     pub site_language: Option<String>,
+    /// The variables of `[site.params]` and `[extra]`, passed to the templates
+    pub site_params: Table,
+    pub languages: BTreeMap<String, LanguageSettings>,
+    pub sections: BTreeMap<String, SectionSettings>,
+    /// The titles of the directories, shown in their indexes and breadcrumbs
+    pub section_titles: BTreeMap<String, String>,
+    pub related: RelatedSettings,
+    pub menus: BTreeMap<String, Vec<MenuEntrySettings>>,
+    pub collections: BTreeMap<String, CollectionSettings>,
+    /// The external commands used as readers
+    pub readers: BTreeMap<String, CommandReaderSettings>,
+    pub assets: AssetSettings,
+    pub minify: MinifySettings,
+    pub sass: SassSettings,
+    pub images: ImageSettings,
+    pub search: SearchSettings,
+    /// The number of words of the summaries extracted from the documents
+    pub summary_words: usize,
+    /// The reading speed used to compute the reading time of the documents
+    pub words_per_minute: usize,
+    /// The unit shown after the reading time
+    pub reading_time_label: String,
+    pub timezone: FixedOffset,
 }
 
 impl Default for Settings {
@@ -40,11 +354,36 @@ impl Default for Settings {
             source_dir: PathBuf::from("."),
             output_dir: PathBuf::from("_output"),
             layouts_dir: PathBuf::from("_layouts"),
-            max_depth: ::std::usize::MAX,
+            data_dir: PathBuf::from("_data"),
+            cache_dir: None,
+            max_depth: usize::MAX,
             follow_links: false,
+            jobs: 0,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            keep: Vec::new(),
+            clean: false,
+            dates_from: None,
             site_title: String::from("Untitled"),
             site_url: String::from("/"),
             site_language: None,
+            site_params: Table::new(),
+            languages: BTreeMap::new(),
+            sections: BTreeMap::new(),
+            section_titles: BTreeMap::new(),
+            related: RelatedSettings::default(),
+            menus: BTreeMap::new(),
+            collections: BTreeMap::new(),
+            readers: BTreeMap::new(),
+            assets: AssetSettings::default(),
+            minify: MinifySettings::default(),
+            sass: SassSettings::default(),
+            images: ImageSettings::default(),
+            search: SearchSettings::default(),
+            summary_words: 50,
+            words_per_minute: 200,
+            reading_time_label: "min read".into(),
+            timezone: FixedOffset::east(0),
         }
     }
 }
@@ -55,6 +394,7 @@ impl Settings {
             source_dir: cwd.join("."),
             output_dir: cwd.join("_output"),
             layouts_dir: cwd.join("_layouts"),
+            data_dir: cwd.join("_data"),
             ..Settings::default()
         }
     }
@@ -80,11 +420,48 @@ impl Settings {
         Settings::from_toml(&toml, &parent_dir)
     }
 
+    /// Returns the directory keeping the caches between the builds. Defaults to
+    /// a directory of `$XDG_CACHE_HOME/helianto` (or `~/.cache/helianto`)
+    /// named after the source directory, so that the caches stay out of it.
+    pub fn cache_dir(&self) -> PathBuf {
+        if let Some(ref cache_dir) = self.cache_dir {
+            return cache_dir.clone();
+        }
+
+        let user_cache_dir = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .unwrap_or_else(env::temp_dir);
+        let source_dir =
+            fs::canonicalize(&self.source_dir).unwrap_or_else(|_| self.source_dir.clone());
+        let hash = content_hash(source_dir.to_string_lossy().as_bytes());
+        user_cache_dir.join("helianto").join(&hash[..16])
+    }
+
+    /// Returns the settings of a directory, inherited from its closest
+    /// configured parent.
+    pub fn section(&self, dir: &str) -> SectionSettings {
+        let mut dir = dir.trim_matches('/');
+        loop {
+            if let Some(section) = self.sections.get(dir) {
+                return *section;
+            }
+            match dir.rfind('/') {
+                Some(index) => dir = &dir[..index],
+                None if !dir.is_empty() => dir = "",
+                None => return SectionSettings::default(),
+            }
+        }
+    }
+
     fn from_toml(toml: &Value, cwd: &Path) -> Result<Self> {
         let mut settings = Settings::with_working_directory(cwd);
 
         macro_rules! get_value {
-            ($key: expr) => { read_value(toml, $key)? };
+            ($key: expr) => {
+                read_value(toml, $key)?
+            };
         }
 
         macro_rules! set_field {
@@ -101,6 +478,108 @@ impl Settings {
             settings.site_language,
             get_value!("site.language").map(Some)
         );
+        for key in ["extra", "site.params"].iter() {
+            if let Some(params) = read_table(toml, key)? {
+                settings.site_params.extend(params.clone());
+            }
+        }
+        set_field!(
+            settings.reading_time_label,
+            get_value!("site.reading_time_label")
+        );
+        set_field!(settings.timezone, read_timezone(toml, "site.timezone")?);
+        set_field!(settings.languages, read_languages(toml, "languages")?);
+        set_field!(settings.sections, read_sections(toml, "sections")?);
+        set_field!(
+            settings.section_titles,
+            read_section_titles(toml, "sections")?
+        );
+        set_field!(settings.related.count, get_value!("related.count"));
+        set_field!(
+            settings.related.directory_weight,
+            read_number(toml, "related.directory_weight")?
+        );
+        set_field!(settings.menus, read_menus(toml, "menu")?);
+        set_field!(settings.collections, read_collections(toml, "collections")?);
+        set_field!(settings.readers, read_readers(toml, "readers")?);
+        set_field!(
+            settings.assets.fingerprint,
+            get_value!("assets.fingerprint")
+        );
+        set_field!(
+            settings.assets.directories,
+            read_strings(toml, "assets.directories")?
+        );
+        set_field!(settings.assets.manifest, get_value!("assets.manifest"));
+        set_field!(
+            settings.assets.hash_length,
+            get_value!("assets.hash_length")
+        );
+        set_field!(
+            settings.assets.bundles,
+            read_bundles(toml, "assets.bundles")?
+        );
+        set_field!(settings.minify.html, get_value!("minify.html"));
+        set_field!(settings.minify.css, get_value!("minify.css"));
+        set_field!(settings.minify.js, get_value!("minify.js"));
+        set_field!(settings.sass.enabled, get_value!("sass.enabled"));
+        set_field!(
+            settings.sass.style,
+            read_choice(
+                toml,
+                "sass.style",
+                &[
+                    ("expanded", SassStyle::Expanded),
+                    ("compressed", SassStyle::Compressed)
+                ]
+            )?
+        );
+        set_field!(settings.images.enabled, get_value!("images.enabled"));
+        set_field!(settings.images.widths, read_widths(toml, "images.widths")?);
+        set_field!(
+            settings.images.formats,
+            read_strings(toml, "images.formats")?
+        );
+        set_field!(
+            settings.images.quality,
+            get_value!("images.quality").map(|quality: i64| quality.clamp(1, 100) as u8)
+        );
+        set_field!(settings.images.sizes, get_value!("images.sizes"));
+        set_field!(
+            settings.sass.load_paths,
+            read_strings(toml, "sass.load_paths")?
+                .map(|paths| paths.into_iter().map(PathBuf::from).collect())
+        );
+        set_field!(settings.summary_words, get_value!("content.summary_words"));
+        set_field!(
+            settings.words_per_minute,
+            get_value!("content.words_per_minute")
+        );
+        set_field!(settings.search.enabled, get_value!("search.enabled"));
+        set_field!(settings.search.output, get_value!("search.output"));
+        set_field!(settings.search.fields, read_strings(toml, "search.fields")?);
+        set_field!(
+            settings.search.exclude,
+            read_strings(toml, "search.exclude")?
+        );
+        set_field!(
+            settings.search.content_length,
+            get_value!("search.content_length")
+        );
+        set_field!(
+            settings.search.inverted_index,
+            get_value!("search.inverted_index")
+        );
+        if let Some(fields) = read_table(toml, "related.fields")? {
+            settings.related.fields = BTreeMap::new();
+            for name in fields.keys() {
+                let weight = read_number(toml, &format!("related.fields.{}", name))?;
+                settings
+                    .related
+                    .fields
+                    .insert(name.to_ascii_lowercase(), weight.unwrap_or(1.0));
+            }
+        }
 
         set_field!(
             settings.output_dir,
@@ -114,8 +593,24 @@ impl Settings {
             settings.layouts_dir,
             read_directory(toml, "compiler.layouts_dir", cwd)?
         );
+        set_field!(
+            settings.data_dir,
+            read_directory(toml, "compiler.data_dir", cwd)?
+        );
+        set_field!(
+            settings.cache_dir,
+            read_directory(toml, "compiler.cache_dir", cwd)?.map(Some)
+        );
         set_field!(settings.max_depth, get_value!("compiler.max_depth"));
         set_field!(settings.follow_links, get_value!("compiler.follow_links"));
+        set_field!(settings.jobs, get_value!("compiler.jobs"));
+        set_field!(settings.include, read_strings(toml, "compiler.include")?);
+        set_field!(settings.exclude, read_strings(toml, "compiler.exclude")?);
+        set_field!(settings.keep, read_strings(toml, "compiler.keep")?);
+        set_field!(
+            settings.dates_from,
+            read_dates_source(toml, "compiler.dates_from")?.map(Some)
+        );
 
         Ok(settings)
     }
@@ -123,7 +618,7 @@ impl Settings {
 
 trait FromToml: 'static + Sized {
     fn type_str() -> &'static str;
-    fn from_toml(toml: &Value) -> Self;
+    fn from_toml(toml: &Value, key: &str) -> Result<Self>;
 }
 
 impl FromToml for String {
@@ -131,8 +626,8 @@ impl FromToml for String {
         "string"
     }
 
-    fn from_toml(toml: &Value) -> String {
-        toml.as_str().unwrap().into()
+    fn from_toml(toml: &Value, _key: &str) -> Result<String> {
+        Ok(toml.as_str().unwrap().into())
     }
 }
 
@@ -141,9 +636,21 @@ impl FromToml for usize {
         "integer"
     }
 
-    fn from_toml(toml: &Value) -> usize {
+    fn from_toml(toml: &Value, key: &str) -> Result<usize> {
         let number = toml.as_integer().unwrap();
-        NumCast::from(number).expect("integer overflow")
+        NumCast::from(number).ok_or_else(|| Error::Settings {
+            message: format!("{} must be a positive integer", key),
+        })
+    }
+}
+
+impl FromToml for i64 {
+    fn type_str() -> &'static str {
+        "integer"
+    }
+
+    fn from_toml(toml: &Value, _key: &str) -> Result<i64> {
+        Ok(toml.as_integer().unwrap())
     }
 }
 
@@ -152,15 +659,20 @@ impl FromToml for bool {
         "boolean"
     }
 
-    fn from_toml(toml: &Value) -> bool {
-        toml.as_bool().unwrap()
+    fn from_toml(toml: &Value, _key: &str) -> Result<bool> {
+        Ok(toml.as_bool().unwrap())
     }
 }
 
+/// Looks up a dotted key such as `site.title` in nested tables.
+fn lookup<'a>(toml: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(toml, |value, part| value.get(part))
+}
+
 fn read_value<T: FromToml>(toml: &Value, key: &str) -> Result<Option<T>> {
-    if let Some(value) = toml.get(key) {
+    if let Some(value) = lookup(toml, key) {
         if value.type_str() == T::type_str() {
-            Ok(Some(FromToml::from_toml(value)))
+            FromToml::from_toml(value, key).map(Some)
         } else {
             Err(Error::Settings {
                 message: format!(
@@ -177,6 +689,52 @@ fn read_value<T: FromToml>(toml: &Value, key: &str) -> Result<Option<T>> {
     }
 }
 
+/// Reads an array of strings.
+fn read_strings(toml: &Value, key: &str) -> Result<Option<Vec<String>>> {
+    let array = match lookup(toml, key) {
+        None => return Ok(None),
+        Some(Value::Array(array)) => array,
+        Some(value) => {
+            return Err(Error::Settings {
+                message: format!(
+                    "found a value of type `{}` instead of an array for the key `{}`",
+                    value.type_str(),
+                    key
+                ),
+            })
+        }
+    };
+
+    array
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| Error::Settings {
+                    message: format!("the key `{}` must only contain strings", key),
+                })
+        })
+        .collect::<Result<Vec<String>>>()
+        .map(Some)
+}
+
+/// Reads a number, which can be written as an integer or as a float.
+fn read_number(toml: &Value, key: &str) -> Result<Option<f64>> {
+    match lookup(toml, key) {
+        None => Ok(None),
+        Some(&Value::Float(value)) => Ok(Some(value)),
+        Some(&Value::Integer(value)) => Ok(Some(value as f64)),
+        Some(value) => Err(Error::Settings {
+            message: format!(
+                "found a value of type `{}` instead of a number for the key `{}`",
+                value.type_str(),
+                key
+            ),
+        }),
+    }
+}
+
 fn read_directory(toml: &Value, key: &str, cwd: &Path) -> Result<Option<PathBuf>> {
     let path: PathBuf = match read_value::<String>(toml, key)? {
         None => return Ok(None),
@@ -189,3 +747,552 @@ fn read_directory(toml: &Value, key: &str, cwd: &Path) -> Result<Option<PathBuf>
         path
     })))
 }
+
+fn read_timezone(toml: &Value, key: &str) -> Result<Option<FixedOffset>> {
+    match read_value::<String>(toml, key)? {
+        None => Ok(None),
+        Some(v) => parse_utc_offset(&v)
+            .map(Some)
+            .ok_or_else(|| Error::Settings {
+                message: format!(
+                    "`{}` is not a valid UTC offset for the key `{}` (expected e.g. `+02:00`)",
+                    v, key
+                ),
+            }),
+    }
+}
+
+fn read_table<'a>(toml: &'a Value, key: &str) -> Result<Option<&'a Table>> {
+    match lookup(toml, key) {
+        None => Ok(None),
+        Some(Value::Table(table)) => Ok(Some(table)),
+        Some(value) => Err(Error::Settings {
+            message: format!(
+                "found a value of type `{}` instead of a table for the key `{}`",
+                value.type_str(),
+                key
+            ),
+        }),
+    }
+}
+
+/// Reads a string which must be one of the `choices`.
+fn read_choice<T: Copy>(toml: &Value, key: &str, choices: &[(&str, T)]) -> Result<Option<T>> {
+    let value = match read_value::<String>(toml, key)? {
+        None => return Ok(None),
+        Some(value) => value,
+    };
+
+    choices
+        .iter()
+        .find(|&&(name, _)| name == value)
+        .map(|&(_, choice)| Some(choice))
+        .ok_or_else(|| Error::Settings {
+            message: format!(
+                "`{}` is not a valid value for the key `{}` (expected one of: {})",
+                value,
+                key,
+                choices
+                    .iter()
+                    .map(|&(name, _)| format!("`{}`", name))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        })
+}
+
+fn read_languages(toml: &Value, key: &str) -> Result<Option<BTreeMap<String, LanguageSettings>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut languages = BTreeMap::new();
+    for (code, value) in table.iter() {
+        languages.insert(
+            code.clone(),
+            LanguageSettings {
+                name: read_value(value, "name")?,
+                title: read_value(value, "title")?,
+                reading_time_label: read_value(value, "reading_time_label")?,
+            },
+        );
+    }
+    Ok(Some(languages))
+}
+
+fn read_dates_source(toml: &Value, key: &str) -> Result<Option<DatesSource>> {
+    read_choice(
+        toml,
+        key,
+        &[
+            ("filesystem", DatesSource::Filesystem),
+            ("git", DatesSource::Git),
+        ],
+    )
+}
+
+fn read_menus(toml: &Value, key: &str) -> Result<Option<BTreeMap<String, Vec<MenuEntrySettings>>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut menus = BTreeMap::new();
+    for (menu_name, value) in table.iter() {
+        let entries = value.as_array().ok_or_else(|| Error::Settings {
+            message: format!(
+                "found a value of type `{}` instead of an array of tables for the key `{}.{}`",
+                value.type_str(),
+                key,
+                menu_name
+            ),
+        })?;
+
+        let mut menu = Vec::new();
+        for entry in entries.iter() {
+            let name: String = read_value(entry, "name")?.unwrap_or_default();
+            let url: String = read_value(entry, "url")?.unwrap_or_default();
+            if name.is_empty() || url.is_empty() {
+                return Err(Error::Settings {
+                    message: format!(
+                        "the entries of the menu `{}` need a `name` and an `url`",
+                        menu_name
+                    ),
+                });
+            }
+
+            menu.push(MenuEntrySettings {
+                name,
+                url,
+                weight: read_value(entry, "weight")?.unwrap_or(0),
+                identifier: read_value(entry, "identifier")?,
+                parent: read_value(entry, "parent")?,
+                language: read_value(entry, "language")?,
+            });
+        }
+        menus.insert(menu_name.clone(), menu);
+    }
+    Ok(Some(menus))
+}
+
+fn read_sort_key(toml: &Value, key: &str) -> Result<Option<SortKey>> {
+    read_choice(
+        toml,
+        key,
+        &[
+            ("created", SortKey::Created),
+            ("modified", SortKey::Modified),
+            ("title", SortKey::Title),
+            ("url", SortKey::Url),
+        ],
+    )
+}
+
+fn read_sort_order(toml: &Value, key: &str) -> Result<Option<SortOrder>> {
+    read_choice(
+        toml,
+        key,
+        &[
+            ("asc", SortOrder::Ascending),
+            ("desc", SortOrder::Descending),
+        ],
+    )
+}
+
+fn read_collections(
+    toml: &Value,
+    key: &str,
+) -> Result<Option<BTreeMap<String, CollectionSettings>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut collections = BTreeMap::new();
+    for (name, value) in table.iter() {
+        let mut collection = CollectionSettings::default();
+        if let Some(pattern) = read_value(value, "pattern")? {
+            collection.pattern = pattern;
+        }
+        collections::compile_pattern(&collection.pattern).map_err(|err| Error::Settings {
+            message: format!("invalid pattern for the collection `{}`: {}", name, err),
+        })?;
+
+        let filters = match lookup(value, "filter") {
+            Some(Value::String(filter)) => vec![filter.clone()],
+            _ => read_strings(value, "filter")?.unwrap_or_default(),
+        };
+        collection.filters = filters
+            .iter()
+            .map(|filter| filter.parse())
+            .collect::<result::Result<Vec<Condition>, String>>()
+            .map_err(|message| Error::Settings {
+                message: format!("invalid filter for the collection `{}`: {}", name, message),
+            })?;
+
+        if let Some(sort_by) = read_sort_key(value, "sort_by")? {
+            collection.sort_by = sort_by;
+        }
+        if let Some(order) = read_sort_order(value, "order")? {
+            collection.order = order;
+        }
+        collection.title = read_value(value, "title")?;
+        collection.index = read_value(value, "index")?;
+        collections.insert(name.clone(), collection);
+    }
+    Ok(Some(collections))
+}
+
+fn read_widths(toml: &Value, key: &str) -> Result<Option<Vec<u32>>> {
+    let error = || Error::Settings {
+        message: format!("`{}` must be an array of positive integers", key),
+    };
+    let widths = match lookup(toml, key) {
+        None => return Ok(None),
+        Some(Value::Array(widths)) => widths,
+        Some(_) => return Err(error()),
+    };
+    widths
+        .iter()
+        .map(|width| match width.as_integer() {
+            Some(width) if width > 0 && width <= u32::MAX as i64 => Ok(width as u32),
+            _ => Err(error()),
+        })
+        .collect::<Result<Vec<u32>>>()
+        .map(Some)
+}
+
+fn read_bundles(toml: &Value, key: &str) -> Result<Option<BTreeMap<String, Vec<String>>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut bundles = BTreeMap::new();
+    for (name, value) in table.iter() {
+        // The names of the bundles are paths, which contain dots and thus
+        // cannot be looked up as keys
+        let files: Option<Vec<String>> = value.as_array().and_then(|files| {
+            files
+                .iter()
+                .map(|file| file.as_str().map(String::from))
+                .collect()
+        });
+        let files = files.ok_or_else(|| Error::Settings {
+            message: format!(
+                "the bundle `{}` of `{}` must be an array of paths",
+                name, key
+            ),
+        })?;
+        bundles.insert(name.trim_start_matches('/').into(), files);
+    }
+    Ok(Some(bundles))
+}
+
+fn read_readers(
+    toml: &Value,
+    key: &str,
+) -> Result<Option<BTreeMap<String, CommandReaderSettings>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut readers = BTreeMap::new();
+    for (name, value) in table.iter() {
+        // The arguments are not split from a string, which could not quote them
+        if let Some(Value::String(_)) = lookup(value, "command") {
+            return Err(Error::Settings {
+                message: format!(
+                    "the command of the reader `{}` must be an array of arguments",
+                    name
+                ),
+            });
+        }
+        let command = read_strings(value, "command")?.unwrap_or_default();
+        let extensions = read_strings(value, "extensions")?.unwrap_or_default();
+        if command.is_empty() || extensions.is_empty() {
+            return Err(Error::Settings {
+                message: format!(
+                    "the reader `{}` must have a `command` and some `extensions`",
+                    name
+                ),
+            });
+        }
+
+        let input = read_choice(
+            value,
+            "input",
+            &[
+                ("argument", CommandInput::Argument),
+                ("stdin", CommandInput::Stdin),
+            ],
+        )?;
+        readers.insert(
+            name.clone(),
+            CommandReaderSettings {
+                command,
+                extensions: extensions
+                    .iter()
+                    .map(|extension| extension.trim_start_matches('.').into())
+                    .collect(),
+                input: input.unwrap_or(CommandInput::Argument),
+            },
+        );
+    }
+    Ok(Some(readers))
+}
+
+fn read_sections(toml: &Value, key: &str) -> Result<Option<BTreeMap<String, SectionSettings>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut sections = BTreeMap::new();
+    for (path, value) in table.iter() {
+        let mut section = SectionSettings::default();
+        if let Some(sort_by) = read_sort_key(value, "sort_by")? {
+            section.sort_by = sort_by;
+        }
+        if let Some(order) = read_sort_order(value, "order")? {
+            section.order = order;
+        }
+        if let Some(full_content) = read_value(value, "full_content")? {
+            section.full_content = full_content;
+        }
+        sections.insert(path.trim_matches('/').into(), section);
+    }
+    Ok(Some(sections))
+}
+
+fn read_section_titles(toml: &Value, key: &str) -> Result<Option<BTreeMap<String, String>>> {
+    let table = match read_table(toml, key)? {
+        None => return Ok(None),
+        Some(table) => table,
+    };
+
+    let mut titles = BTreeMap::new();
+    for (path, value) in table.iter() {
+        if let Some(title) = read_value::<String>(value, "title")? {
+            titles.insert(path.trim_matches('/').into(), title);
+        }
+    }
+    Ok(Some(titles))
+}
+
+#[test]
+fn test_from_toml() {
+    let toml: Value = toml::de::from_str(
+        r#"
+        [site]
+        title = "Foo"
+        timezone = "+01:00"
+
+        [site.params]
+        author = "Jane"
+
+        [extra]
+        author = "John"
+        analytics = "UA-1"
+
+        [compiler]
+        max_depth = 3
+        cache_dir = "/var/cache/helianto"
+        dates_from = "git"
+        include = [".well-known"]
+        exclude = ["node_modules/", "*.log"]
+        keep = [".git", ".nojekyll"]
+
+        [content]
+        summary_words = 20
+
+        [sections.blog]
+        title = "Blog"
+        sort_by = "title"
+        order = "asc"
+        full_content = true
+
+        [related]
+        count = 3
+        directory_weight = 0
+
+        [related.fields]
+        tags = 2.5
+
+        [[menu.main]]
+        name = "Blog"
+        url = "/blog/"
+        weight = -1
+
+        [assets]
+        fingerprint = true
+        directories = ["static"]
+
+        [assets.bundles]
+        "css/site.css" = ["css/normalize.css", "css/custom.css"]
+
+        [minify]
+        css = true
+
+        [images]
+        enabled = true
+        widths = [320, 640]
+        formats = []
+
+        [sass]
+        style = "compressed"
+        load_paths = ["node_modules"]
+
+        [readers.asciidoc]
+        command = ["asciidoctor", "-o", "-"]
+        extensions = ["adoc", ".asciidoc"]
+
+        [readers.script]
+        command = ["./convert.py"]
+        extensions = ["txt"]
+        input = "stdin"
+
+        [collections.posts]
+        pattern = "blog/**/*.md"
+        filter = "draft != true"
+        index = "blog/archives.html"
+
+        [search]
+        enabled = true
+        fields = ["title", "content"]
+        exclude = ["drafts/"]
+
+        [languages.fr]
+        name = "Français"
+        title = "Bar"
+        reading_time_label = "min de lecture"
+        "#,
+    )
+    .unwrap();
+    let settings = Settings::from_toml(&toml, Path::new("/tmp")).unwrap();
+    assert_eq!(settings.site_title, "Foo");
+    assert_eq!(settings.timezone, FixedOffset::east(3600));
+    assert_eq!(settings.site_params["author"].as_str(), Some("Jane"));
+    assert_eq!(settings.site_params["analytics"].as_str(), Some("UA-1"));
+    assert_eq!(settings.max_depth, 3);
+    assert_eq!(settings.cache_dir(), Path::new("/var/cache/helianto"));
+    assert!(!Settings::with_working_directory(Path::new("/tmp/site"))
+        .cache_dir()
+        .starts_with("/tmp/site"));
+    assert_eq!(settings.dates_from, Some(DatesSource::Git));
+    assert_eq!(settings.include, [".well-known"]);
+    assert_eq!(settings.exclude, ["node_modules/", "*.log"]);
+    assert_eq!(settings.keep, [".git", ".nojekyll"]);
+    assert_eq!(
+        settings.section("blog/2020"),
+        SectionSettings {
+            sort_by: SortKey::Title,
+            order: SortOrder::Ascending,
+            full_content: true,
+        }
+    );
+    assert_eq!(settings.section("about"), SectionSettings::default());
+    assert_eq!(settings.section_titles.get("blog"), Some(&"Blog".into()));
+    assert_eq!(
+        settings.menus["main"],
+        [MenuEntrySettings {
+            name: "Blog".into(),
+            url: "/blog/".into(),
+            weight: -1,
+            ..MenuEntrySettings::default()
+        }]
+    );
+    assert_eq!(settings.summary_words, 20);
+    assert_eq!(settings.words_per_minute, 200);
+    let posts = &settings.collections["posts"];
+    assert_eq!(posts.pattern, "blog/**/*.md");
+    assert_eq!(posts.filters, ["draft != true".parse().unwrap()]);
+    assert_eq!(posts.order, SortOrder::Descending);
+    assert_eq!(posts.index, Some("blog/archives.html".into()));
+    assert_eq!(
+        settings.readers["asciidoc"],
+        CommandReaderSettings {
+            command: vec!["asciidoctor".into(), "-o".into(), "-".into()],
+            extensions: vec!["adoc".into(), "asciidoc".into()],
+            input: CommandInput::Argument,
+        }
+    );
+    assert_eq!(settings.readers["script"].command, ["./convert.py"]);
+    assert_eq!(settings.readers["script"].input, CommandInput::Stdin);
+    assert_eq!(
+        settings.assets,
+        AssetSettings {
+            fingerprint: true,
+            directories: vec!["static".into()],
+            bundles: vec![(
+                "css/site.css".into(),
+                vec!["css/normalize.css".into(), "css/custom.css".into()]
+            )]
+            .into_iter()
+            .collect(),
+            ..AssetSettings::default()
+        }
+    );
+    assert_eq!(
+        settings.minify,
+        MinifySettings {
+            css: true,
+            ..MinifySettings::default()
+        }
+    );
+    assert_eq!(
+        settings.sass,
+        SassSettings {
+            enabled: true,
+            style: SassStyle::Compressed,
+            load_paths: vec![PathBuf::from("node_modules")],
+        }
+    );
+    assert_eq!(
+        settings.images,
+        ImageSettings {
+            enabled: true,
+            widths: vec![320, 640],
+            formats: vec![],
+            ..ImageSettings::default()
+        }
+    );
+    assert!(settings.search.enabled);
+    assert_eq!(settings.search.fields, ["title", "content"]);
+    assert_eq!(settings.search.exclude, ["drafts/"]);
+    assert_eq!(settings.search.output, "search.json");
+    assert_eq!(settings.related.count, 3);
+    assert_eq!(settings.related.directory_weight, 0.0);
+    assert_eq!(settings.related.fields.get("tags"), Some(&2.5));
+    assert_eq!(settings.related.fields.get("keywords"), None);
+    assert_eq!(
+        settings.languages.get("fr"),
+        Some(&LanguageSettings {
+            name: Some("Français".into()),
+            title: Some("Bar".into()),
+            reading_time_label: Some("min de lecture".into()),
+        })
+    );
+}
+
+#[test]
+fn test_negative_integer() {
+    let toml: Value = toml::de::from_str("[compiler]\njobs = -1").unwrap();
+    match Settings::from_toml(&toml, Path::new("/tmp")) {
+        Err(Error::Settings { message }) => {
+            assert_eq!(message, "compiler.jobs must be a positive integer")
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_reader_command_string() {
+    let toml: Value = toml::de::from_str(
+        "[readers.asciidoc]\ncommand = \"asciidoctor -o -\"\nextensions = [\"adoc\"]",
+    )
+    .unwrap();
+    assert!(Settings::from_toml(&toml, Path::new("/tmp")).is_err());
+}
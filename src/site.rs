@@ -15,14 +15,31 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::Settings;
+use crate::utils;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::default::Default;
+use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// One of the languages of a multilingual website.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteLanguage {
+    pub code: String,
+    pub name: String,
+    pub title: String,
+    pub reading_time_label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Site {
     pub title: String,
     pub language: Option<String>,
     pub url: String,
+    pub languages: Vec<SiteLanguage>,
+    /// The variables of `[site.params]` and `[extra]`
+    pub params: Arc<Map<String, Value>>,
+    /// The unit shown after the reading time of the documents
+    pub reading_time_label: String,
 }
 
 impl Default for Site {
@@ -31,18 +48,103 @@ impl Default for Site {
             title: "Untitled website".into(),
             language: None,
             url: "/".into(),
+            languages: Vec::new(),
+            params: Arc::default(),
+            reading_time_label: "min read".into(),
         }
     }
 }
 
 impl Site {
     pub fn new(setting: &Settings) -> Site {
-        let mut site = Site::default();
-        site.title = setting.site_title.clone();
-        site.url = setting.site_url.clone();
-        if let Some(ref language) = setting.site_language {
-            site.language = Some(language.clone());
+        let mut languages: Vec<SiteLanguage> = setting
+            .languages
+            .iter()
+            .map(|(code, language)| SiteLanguage {
+                code: code.clone(),
+                name: language.name.clone().unwrap_or_else(|| code.clone()),
+                title: language
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| setting.site_title.clone()),
+                reading_time_label: language
+                    .reading_time_label
+                    .clone()
+                    .unwrap_or_else(|| setting.reading_time_label.clone()),
+            })
+            .collect();
+
+        if let Some(ref code) = setting.site_language {
+            if !setting.languages.contains_key(code) && !languages.is_empty() {
+                languages.insert(
+                    0,
+                    SiteLanguage {
+                        code: code.clone(),
+                        name: code.clone(),
+                        title: setting.site_title.clone(),
+                        reading_time_label: setting.reading_time_label.clone(),
+                    },
+                );
+            }
+        }
+
+        Site {
+            title: setting.site_title.clone(),
+            url: setting.site_url.clone(),
+            language: setting.site_language.clone(),
+            languages,
+            params: Arc::new(
+                setting
+                    .site_params
+                    .iter()
+                    .map(|(key, value)| (key.clone(), utils::toml_to_json(value)))
+                    .collect(),
+            ),
+            reading_time_label: setting.reading_time_label.clone(),
+        }
+    }
+
+    /// Returns the website as seen from a page written in `language`.
+    pub fn localized(&self, language: Option<&str>) -> Site {
+        let mut site = self.clone();
+        if let Some(localized) = self
+            .languages
+            .iter()
+            .find(|site_language| Some(site_language.code.as_str()) == language)
+        {
+            site.title = localized.title.clone();
+            site.language = Some(localized.code.clone());
+            site.reading_time_label = localized.reading_time_label.clone();
         }
         site
     }
 }
+
+#[test]
+fn test_localized() {
+    let mut settings = Settings {
+        site_title: "Foo".into(),
+        site_language: Some("en".into()),
+        ..Settings::default()
+    };
+    settings.languages.insert(
+        "fr".into(),
+        crate::settings::LanguageSettings {
+            name: Some("Français".into()),
+            title: Some("Truc".into()),
+            reading_time_label: Some("min de lecture".into()),
+        },
+    );
+    let site = Site::new(&settings);
+
+    assert_eq!(site.languages.len(), 2);
+    assert_eq!(site.localized(Some("fr")).title, "Truc");
+    assert_eq!(site.localized(Some("fr")).language, Some("fr".into()));
+    assert_eq!(
+        site.localized(Some("fr")).reading_time_label,
+        "min de lecture"
+    );
+    assert_eq!(site.localized(Some("en")).title, "Foo");
+    assert_eq!(site.localized(Some("en")).reading_time_label, "min read");
+    assert_eq!(site.localized(None).title, "Foo");
+}
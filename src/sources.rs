@@ -0,0 +1,173 @@
+// Helianto -- static website generator
+// Copyright © 2015-2016 Mickaël RAYBAUD-ROIG
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::utils;
+use super::{Error, Result, Settings};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::DirEntry;
+
+/// The file listing the ignored files, at the root of the source directory.
+pub const IGNORE_FILE: &str = ".heliantoignore";
+
+/// The files always excluded, before the patterns of `compiler.exclude`.
+pub const DEFAULT_EXCLUDE: &[&str] = &["/helianto.toml", "/README.md", "node_modules/"];
+
+/// Selects the files of the source directory which are read or copied.
+pub struct SourceFilter {
+    source_dir: PathBuf,
+    /// The output directory, when it is inside the source directory
+    output_dir: Option<PathBuf>,
+    include: Gitignore,
+    exclude: Gitignore,
+}
+
+/// Builds a matcher from patterns using the syntax of the `.gitignore` files.
+/// The patterns of the settings follow the default ones, which they can
+/// negate with `!`.
+pub fn build_matcher(
+    source_dir: &Path,
+    key: &str,
+    defaults: &[&str],
+    patterns: &[String],
+) -> Result<GitignoreBuilder> {
+    let mut builder = GitignoreBuilder::new(source_dir);
+    for pattern in defaults
+        .iter()
+        .copied()
+        .chain(patterns.iter().map(String::as_str))
+    {
+        builder
+            .add_line(None, pattern)
+            .map_err(|err| Error::Settings {
+                message: format!("invalid pattern in `{}`: {}", key, err),
+            })?;
+    }
+    Ok(builder)
+}
+
+impl SourceFilter {
+    pub fn new(settings: &Settings) -> Result<SourceFilter> {
+        let source_dir = &settings.source_dir;
+        let include = build_matcher(source_dir, "compiler.include", &[], &settings.include)?;
+        let mut exclude = build_matcher(
+            source_dir,
+            "compiler.exclude",
+            DEFAULT_EXCLUDE,
+            &settings.exclude,
+        )?;
+
+        let ignore_file = source_dir.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            if let Some(err) = exclude.add(&ignore_file) {
+                return Err(Error::Settings {
+                    message: format!("could not read {}: {}", ignore_file.display(), err),
+                });
+            }
+        }
+
+        let build = |builder: GitignoreBuilder| {
+            builder.build().map_err(|err| Error::Settings {
+                message: format!("invalid ignore pattern: {}", err),
+            })
+        };
+        let output_dir = fs::canonicalize(&settings.output_dir)
+            .ok()
+            .and_then(|output_dir| {
+                let source_dir = fs::canonicalize(source_dir).ok()?;
+                output_dir.strip_prefix(source_dir).ok().map(PathBuf::from)
+            });
+
+        Ok(SourceFilter {
+            source_dir: source_dir.clone(),
+            output_dir,
+            include: build(include)?,
+            exclude: build(exclude)?,
+        })
+    }
+
+    /// Checks whether a file or a directory of the source directory is read.
+    /// The hidden files are skipped unless they are included, and the
+    /// excluded files are always skipped.
+    pub fn accepts(&self, path: &Path, is_dir: bool) -> bool {
+        let relpath = path.strip_prefix(&self.source_dir).unwrap_or(path);
+        if relpath.as_os_str().is_empty() {
+            return true;
+        }
+        if self.output_dir.as_deref() == Some(relpath) {
+            return false;
+        }
+        if self
+            .exclude
+            .matched_path_or_any_parents(relpath, is_dir)
+            .is_ignore()
+        {
+            return false;
+        }
+
+        utils::is_public(&path)
+            || self
+                .include
+                .matched_path_or_any_parents(relpath, is_dir)
+                .is_ignore()
+    }
+
+    pub fn accepts_entry(&self, entry: &DirEntry) -> bool {
+        let file_type = entry.file_type();
+        (file_type.is_dir() || file_type.is_file())
+            && self.accepts(entry.path(), file_type.is_dir())
+    }
+}
+
+#[test]
+fn test_source_filter() {
+    let dir = utils::TempDir::new("sources");
+    fs::create_dir_all(dir.join("public")).unwrap();
+    fs::write(
+        dir.join(IGNORE_FILE),
+        "# Drafts\ndrafts/\n*.bak\n!keep.bak\n",
+    )
+    .unwrap();
+
+    let settings = Settings {
+        include: vec![".well-known".into(), "_redirects".into()],
+        exclude: vec!["*.log".into(), "!/node_modules/".into()],
+        output_dir: dir.join("public"),
+        ..Settings::with_working_directory(&dir)
+    };
+    let filter = SourceFilter::new(&settings).unwrap();
+    let accepts = |path: &str, is_dir: bool| filter.accepts(&dir.join(path), is_dir);
+
+    assert!(accepts("index.md", false));
+    assert!(accepts("blog/helianto.toml", false));
+    assert!(!accepts("helianto.toml", false));
+    assert!(!accepts("README.md", false));
+    assert!(accepts("blog/README.md", false));
+    assert!(!accepts("debug.log", false));
+    assert!(!accepts("public", true));
+    assert!(accepts("blog/public", true));
+    assert!(accepts("node_modules", true));
+    assert!(!accepts("lib/node_modules", true));
+    assert!(!accepts("drafts", true));
+    assert!(!accepts("notes.bak", false));
+    assert!(accepts("keep.bak", false));
+    assert!(!accepts(".git", true));
+    assert!(!accepts("_layouts", true));
+    assert!(accepts(".well-known", true));
+    assert!(accepts(".well-known/.security.txt", false));
+    assert!(accepts("_redirects", false));
+}
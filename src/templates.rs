@@ -14,28 +14,302 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::{Document, Site};
+use super::assets::Manifest;
+use super::collections::Collections;
+use super::images::{self, ImageInfo, Images};
+use super::menus::{self, Menus};
+use super::sections;
+use super::{Document, DocumentContent, DocumentMetadata, Settings, Site};
 use chrono::DateTime;
 use handlebars::{
-    self, Handlebars, Helper, HelperResult, JsonRender, Output, RenderContext, RenderError,
+    self, html_escape, Handlebars, Helper, HelperResult, JsonRender, Output, RenderContext,
+    RenderError,
 };
 use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use std::sync::Arc;
 use walkdir::{DirEntry, WalkDir};
 
+/// All the documents and data files of the website, as exposed to the
+/// templates.
+#[derive(Debug, Default, Serialize)]
+pub struct Catalog {
+    /// The documents, from the most recent to the oldest
+    pub documents: Vec<Arc<DocumentMetadata>>,
+    /// The documents of each directory and of its subdirectories
+    pub sections: BTreeMap<String, Vec<Arc<DocumentMetadata>>>,
+    /// The documents of each collection declared in the settings
+    pub collections: Collections,
+    /// The navigation menus, before they are localized for a page
+    #[serde(skip)]
+    pub menus: Menus,
+    /// The content of the data files
+    pub data: serde_json::Value,
+    /// The fingerprinted path of each asset
+    pub assets: Manifest,
+    /// The variants of each processed image
+    pub images: Images,
+    /// The default `sizes` attribute of the responsive images
+    pub image_sizes: String,
+}
+
+impl Catalog {
+    pub fn new<I>(documents: I, settings: &Settings) -> Catalog
+    where
+        I: IntoIterator<Item = Arc<DocumentMetadata>>,
+    {
+        let mut documents: Vec<Arc<DocumentMetadata>> = documents.into_iter().collect();
+        documents.sort_by(|a, b| b.created.cmp(&a.created));
+
+        let mut sections: BTreeMap<String, Vec<Arc<DocumentMetadata>>> = BTreeMap::new();
+        for document in documents.iter() {
+            let mut path = Path::new(&document.url);
+            while let Some(parent) = path.parent() {
+                if let Some(section) = parent.to_str().filter(|s| !s.is_empty()) {
+                    sections
+                        .entry(section.into())
+                        .or_default()
+                        .push(document.clone());
+                }
+                path = parent;
+            }
+        }
+
+        for (section, documents) in sections.iter_mut() {
+            sections::sort(documents, settings.section(section));
+        }
+
+        let menus = menus::build_menus(settings, &documents);
+
+        Catalog {
+            documents,
+            sections,
+            collections: Collections::new(),
+            menus,
+            data: serde_json::Value::default(),
+            assets: Manifest::new(),
+            images: Images::new(),
+            image_sizes: settings.images.sizes.clone(),
+        }
+    }
+}
+
+/// The part of `site` which depends on the page. The catalog is added to it
+/// by `PageContext`.
 #[derive(Debug, Serialize)]
-pub struct Context<'a> {
+pub struct SiteContext<'a> {
+    #[serde(flatten)]
     pub site: &'a Site,
+    /// The navigation menus, as seen from the current page
+    pub menus: Menus,
+}
+
+/// The HTML content of the documents listed by the indexes showing it, by URL.
+pub type Contents<'a> = HashMap<&'a str, &'a str>;
+
+/// A document listed in an index, along with its content.
+#[derive(Debug, Serialize)]
+pub struct IndexEntry<'a> {
+    #[serde(flatten)]
+    pub metadata: &'a DocumentMetadata,
+    pub content: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Page<'a> {
+    Document(&'a Document),
+    /// An index listing the content of its documents
+    Index {
+        #[serde(flatten)]
+        metadata: &'a DocumentMetadata,
+        documents: Vec<IndexEntry<'a>>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Context<'a> {
+    pub site: SiteContext<'a>,
     #[serde(rename = "page")]
-    pub document: &'a Document,
+    pub document: Page<'a>,
 }
 
 impl<'a> Context<'a> {
-    pub fn new<'b>(site: &'b Site, document: &'b Document) -> Context<'b> {
-        Context { site, document }
+    /// Creates the context of a document. The documents listed by an index
+    /// include their content when `contents` is given.
+    pub fn new(
+        site: &'a Site,
+        catalog: &'a Catalog,
+        document: &'a Document,
+        contents: Option<&Contents<'a>>,
+    ) -> Context<'a> {
+        let page = match (&document.content, contents) {
+            (DocumentContent::Index { documents }, Some(contents)) => Page::Index {
+                metadata: &document.metadata,
+                documents: documents
+                    .iter()
+                    .map(|metadata| IndexEntry {
+                        metadata,
+                        content: contents.get(metadata.url.as_str()).copied(),
+                    })
+                    .collect(),
+            },
+            _ => Page::Document(document),
+        };
+
+        Context {
+            site: SiteContext {
+                site,
+                menus: menus::localize(
+                    &catalog.menus,
+                    &document.metadata.url,
+                    document.metadata.language.as_deref(),
+                    &site.url,
+                ),
+            },
+            document: page,
+        }
     }
 }
 
+/// The data of the templates, reused from one page to the next.
+///
+/// The catalog is serialized only once, then the page and the localized
+/// website are replaced before rendering each page.
+#[derive(Clone)]
+pub struct PageContext(handlebars::Context);
+
+impl PageContext {
+    pub fn new(catalog: &Catalog) -> Result<PageContext, RenderError> {
+        handlebars::Context::wraps(serde_json::json!({ "site": catalog })).map(PageContext)
+    }
+
+    pub fn render(
+        &mut self,
+        handlebars: &Handlebars,
+        name: &str,
+        context: &Context,
+    ) -> Result<String, RenderError> {
+        let page =
+            serde_json::to_value(context).map_err(|err| RenderError::from_error(name, err))?;
+
+        if let (Value::Object(data), Value::Object(page)) = (self.0.data_mut(), page) {
+            for (key, value) in page {
+                match (data.get_mut(&key), value) {
+                    (Some(Value::Object(site)), Value::Object(fields)) => site.extend(fields),
+                    (_, value) => {
+                        data.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        handlebars.render_with_context(name, &self.0)
+    }
+}
+
+#[test]
+fn test_catalog() {
+    let document = |url: &str, created: &str| {
+        Arc::new(DocumentMetadata {
+            url: url.into(),
+            created: crate::utils::DateTime::from_string(created),
+            ..DocumentMetadata::default()
+        })
+    };
+    let catalog = Catalog::new(
+        vec![
+            document("about.html", "2019-01-01T00:00:00Z"),
+            document("blog/2020/foo.html", "2020-01-01T00:00:00Z"),
+            document("blog/bar.html", "2021-01-01T00:00:00Z"),
+        ],
+        &Settings::default(),
+    );
+
+    let urls = |documents: &[Arc<DocumentMetadata>]| -> Vec<String> {
+        documents.iter().map(|d| d.url.clone()).collect()
+    };
+    assert_eq!(
+        urls(&catalog.documents),
+        ["blog/bar.html", "blog/2020/foo.html", "about.html"]
+    );
+    assert_eq!(
+        urls(&catalog.sections["blog"]),
+        ["blog/bar.html", "blog/2020/foo.html"]
+    );
+    assert_eq!(urls(&catalog.sections["blog/2020"]), ["blog/2020/foo.html"]);
+    assert_eq!(catalog.sections.len(), 2);
+}
+
+#[test]
+fn test_index_context() {
+    let post = Arc::new(DocumentMetadata {
+        url: "blog/post.html".into(),
+        ..DocumentMetadata::default()
+    });
+    let index = Document::new(
+        DocumentMetadata {
+            url: "blog/index.html".into(),
+            ..DocumentMetadata::default()
+        },
+        vec![post].into_iter().collect(),
+    );
+    let site = Site::default();
+    let catalog = Catalog::default();
+    let mut contents = Contents::new();
+    contents.insert("blog/post.html", "<p>Hello</p>");
+
+    let page = |contents| {
+        let context = Context::new(&site, &catalog, &index, contents);
+        serde_json::to_value(&context).unwrap()["page"].clone()
+    };
+    assert_eq!(
+        page(Some(&contents))["documents"][0]["content"],
+        "<p>Hello</p>"
+    );
+    assert_eq!(page(Some(&contents))["url"], "blog/index.html");
+    assert!(page(None)["documents"][0].get("content").is_none());
+}
+
+#[test]
+fn test_page_context() {
+    let mut handlebars = Handlebars::new();
+    handlebars
+        .register_template_string(
+            "page.html",
+            "{{site.title}} {{page.title}} {{#each site.documents}}{{url}} {{/each}}",
+        )
+        .unwrap();
+
+    let metadata = |url: &str, title: &str| DocumentMetadata {
+        url: url.into(),
+        title: title.into(),
+        ..DocumentMetadata::default()
+    };
+    let foo = Document::new(metadata("foo.html", "Foo"), String::new().into());
+    let bar = Document::new(metadata("bar.html", "Bar"), String::new().into());
+    let catalog = Catalog::new(
+        vec![
+            Arc::new(foo.metadata.clone()),
+            Arc::new(bar.metadata.clone()),
+        ],
+        &Settings::default(),
+    );
+    let site = Site::default();
+
+    let mut page_context = PageContext::new(&catalog).unwrap();
+    let mut render = |document| {
+        let context = Context::new(&site, &catalog, document, None);
+        page_context
+            .render(&handlebars, "page.html", &context)
+            .unwrap()
+    };
+    assert_eq!(render(&foo), "Untitled website Foo foo.html bar.html ");
+    assert_eq!(render(&bar), "Untitled website Bar foo.html bar.html ");
+}
+
 fn date_helper(
     h: &Helper,
     _: &Handlebars,
@@ -107,11 +381,138 @@ fn join_helper(
     Ok(())
 }
 
+/// Returns the URL of an asset, using its fingerprinted path when there is one.
+fn asset_helper(
+    h: &Helper,
+    _: &Handlebars,
+    c: &handlebars::Context,
+    _rc: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let path = h
+        .param(0)
+        .and_then(|v| v.value().as_str())
+        .ok_or(RenderError::new("Param not found for helper \"asset\""))?
+        .trim_start_matches('/');
+
+    let site = &c.data()["site"];
+    out.write(&format!(
+        "{}{}",
+        site["url"].as_str().unwrap_or("/"),
+        site["assets"][path].as_str().unwrap_or(path)
+    ))?;
+
+    Ok(())
+}
+
+/// Displays an image with its responsive variants, the other hash parameters
+/// being written as attributes of the `<img>` tag.
+fn image_helper(
+    h: &Helper,
+    _: &Handlebars,
+    c: &handlebars::Context,
+    _rc: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let path = h
+        .param(0)
+        .and_then(|v| v.value().as_str())
+        .ok_or(RenderError::new("Param not found for helper \"image\""))?
+        .trim_start_matches('/');
+
+    let site = &c.data()["site"];
+    let site_url = site["url"].as_str().unwrap_or("/");
+    let mut sizes = site["image_sizes"].as_str().unwrap_or("100vw").to_string();
+    let mut attributes = Vec::new();
+    for (name, value) in h.hash() {
+        let value = match value.value() {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        match *name {
+            "sizes" => sizes = value,
+            name => attributes.push((name.to_string(), value)),
+        }
+    }
+
+    let info = serde_json::from_value::<ImageInfo>(site["images"][path].clone());
+    let html = match info {
+        Ok(info) => images::markup(&info, &attributes, &sizes, site_url),
+        Err(_) => {
+            let mut img = format!("<img src=\"{}{}\"", site_url, html_escape(path));
+            for (name, value) in attributes.iter() {
+                img.push_str(&format!(" {}=\"{}\"", name, html_escape(value)));
+            }
+            img + " />"
+        }
+    };
+    out.write(&html)?;
+
+    Ok(())
+}
+
 pub fn register_helpers(handlebars: &mut Handlebars<'static>) {
+    handlebars.register_helper("asset", Box::new(asset_helper));
+    handlebars.register_helper("image", Box::new(image_helper));
     handlebars.register_helper("date", Box::new(date_helper));
     handlebars.register_helper("join", Box::new(join_helper));
 }
 
+#[test]
+fn test_asset_helper() {
+    let mut handlebars = Handlebars::new();
+    register_helpers(&mut handlebars);
+    let context = serde_json::json!({
+        "site": {"url": "/blog/", "assets": {"css/custom.css": "css/custom.0a1b.css"}}
+    });
+    let render = |template: &str| handlebars.render_template(template, &context).unwrap();
+
+    assert_eq!(
+        render("{{asset \"css/custom.css\"}}"),
+        "/blog/css/custom.0a1b.css"
+    );
+    assert_eq!(
+        render("{{asset \"/css/custom.css\"}}"),
+        "/blog/css/custom.0a1b.css"
+    );
+    assert_eq!(render("{{asset \"js/app.js\"}}"), "/blog/js/app.js");
+}
+
+#[test]
+fn test_image_helper() {
+    let mut handlebars = Handlebars::new();
+    register_helpers(&mut handlebars);
+    let context = serde_json::json!({
+        "site": {
+            "url": "/",
+            "image_sizes": "50vw",
+            "images": {
+                "img/a.png": {
+                    "url": "img/a.png",
+                    "width": 100,
+                    "height": 50,
+                    "mime": "image/png",
+                    "variants": [
+                        {"url": "img/a-40w.png", "width": 40, "height": 20, "mime": "image/png"},
+                        {"url": "img/a.png", "width": 100, "height": 50, "mime": "image/png"}
+                    ]
+                }
+            }
+        }
+    });
+    let render = |template: &str| handlebars.render_template(template, &context).unwrap();
+
+    assert_eq!(
+        render("{{image \"img/a.png\" alt=\"A & B\"}}"),
+        "<img src=\"/img/a.png\" srcset=\"/img/a-40w.png 40w, /img/a.png 100w\" sizes=\"50vw\" \
+         width=\"100\" height=\"50\" alt=\"A &amp; B\" />"
+    );
+    assert_eq!(
+        render("{{image \"img/b.gif\" alt=\"B\" sizes=\"10em\"}}"),
+        "<img src=\"/img/b.gif\" alt=\"B\" />"
+    );
+}
+
 pub struct Loader<'r> {
     pub registry: &'r mut Handlebars<'static>,
 }
@@ -137,7 +538,7 @@ impl<'r> Loader<'r> {
     pub fn load_templates(&mut self, templates_dir: &Path) {
         let iter = WalkDir::new(templates_dir)
             .into_iter()
-            .filter_entry(|e| filter_templates(e));
+            .filter_entry(filter_templates);
 
         for result in iter {
             let path = match result {
@@ -176,9 +577,9 @@ fn template_name(templates_dir: &Path, template_path: &Path) -> Option<String> {
     template_path
         .with_extension("")
         .strip_prefix(templates_dir)
-        .or_else(|e| {
+        .map_err(|e| {
             debug!("Path::strip_prefix() -> {}", e);
-            Err(e)
+            e
         })
         .ok()
         .and_then(|p| p.to_str())
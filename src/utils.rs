@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use chrono::{self, FixedOffset};
+use chrono::{self, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::path::{Component, Path, PathBuf};
 
@@ -36,6 +36,26 @@ pub fn is_public<S: AsRef<Path> + Sized>(path: &S) -> bool {
             .unwrap_or(false)
 }
 
+/// Converts a TOML value to JSON, writing the dates as strings.
+pub fn toml_to_json(value: &toml::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match *value {
+        toml::Value::String(ref string) => Value::from(string.as_str()),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => Value::from(float),
+        toml::Value::Boolean(boolean) => Value::from(boolean),
+        toml::Value::Datetime(ref datetime) => Value::from(datetime.to_string()),
+        toml::Value::Array(ref array) => array.iter().map(toml_to_json).collect(),
+        toml::Value::Table(ref table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.clone(), toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
 /// Remove the prefixes from a path
 pub fn remove_path_prefix<S: AsRef<Path>>(path: S) -> PathBuf {
     path.as_ref()
@@ -64,6 +84,47 @@ pub fn remove_leading_dot<S: AsRef<Path>>(path: S) -> PathBuf {
     }
 }
 
+/// A temporary directory for the tests, removed when it goes out of scope,
+/// even when an assertion fails.
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("helianto-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_toml_to_json() {
+    let value: toml::Value =
+        toml::from_str("date = 2020-05-01\nlist = [1, 2.5]\n[nested]\nok = true").unwrap();
+    assert_eq!(
+        toml_to_json(&value),
+        serde_json::json!({"date": "2020-05-01", "list": [1, 2.5], "nested": {"ok": true}})
+    );
+}
+
 #[test]
 fn test_remove_leading_dot() {
     const PATHS: &[(&str, &str)] = &[
@@ -103,12 +164,132 @@ where
     serializer.serialize_str(date.to_rfc3339().as_str())
 }
 
+/// Examples of the date formats accepted in the metadata of the documents.
+pub const DATE_FORMATS: &[&str] = &[
+    "2020-05-01T14:00:00+02:00",
+    "Fri, 01 May 2020 14:00:00 +0200",
+    "2020-05-01 14:00:00 +02:00",
+    "2020-05-01 14:00:00",
+    "2020-05-01 14:00",
+    "2020-05-01",
+    "2020/05/01",
+    "1 May 2020",
+    "May 1, 2020",
+];
+
+const OFFSET_DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S %:z", "%Y-%m-%d %H:%M %:z"];
+
+const NAIVE_DATE_TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+const NAIVE_DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%d %B %Y", "%B %d, %Y"];
+
 impl DateTime {
+    /// Parses a RFC 3339 date.
     pub fn from_string(s: &str) -> Option<DateTime> {
         chrono::DateTime::parse_from_rfc3339(s).ok().map(DateTime)
     }
+
+    /// Parses a date in one of the `DATE_FORMATS`.
+    ///
+    /// Dates without an UTC offset are interpreted in the timezone `timezone`.
+    pub fn parse(s: &str, timezone: &FixedOffset) -> Option<DateTime> {
+        let s = s.trim();
+
+        if let Some(date) = DateTime::from_string(s) {
+            return Some(date);
+        }
+
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(s) {
+            return Some(DateTime(date));
+        }
+
+        for format in OFFSET_DATE_TIME_FORMATS {
+            if let Ok(date) = chrono::DateTime::parse_from_str(s, format) {
+                return Some(DateTime(date));
+            }
+        }
+
+        NAIVE_DATE_TIME_FORMATS
+            .iter()
+            .filter_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+            .chain(
+                NAIVE_DATE_FORMATS
+                    .iter()
+                    .filter_map(|format| NaiveDate::parse_from_str(s, format).ok())
+                    .map(|date| date.and_hms(0, 0, 0)),
+            )
+            .next()
+            .and_then(|naive| timezone.from_local_datetime(&naive).single())
+            .map(DateTime)
+    }
+}
+
+impl From<chrono::DateTime<FixedOffset>> for DateTime {
+    fn from(date: chrono::DateTime<FixedOffset>) -> DateTime {
+        DateTime(date)
+    }
+}
+
+/// Parses an UTC offset such as `+02:00`, `-0500` or `UTC`.
+pub fn parse_utc_offset(s: &str) -> Option<FixedOffset> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, rest) = match s.chars().next() {
+        Some('+') => (1, &s[1..]),
+        Some('-') => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[test]
+fn test_parse_date() {
+    let utc = FixedOffset::east(0);
+    let expected = DateTime::from_string("2020-05-01T00:00:00+00:00");
+
+    for input in &["2020-05-01", "2020/05/01", "1 May 2020", "May 1, 2020"] {
+        assert_eq!(DateTime::parse(input, &utc), expected, "{}", input);
+    }
+
+    assert_eq!(
+        DateTime::parse("2020-05-01 14:00", &FixedOffset::east(2 * 3600)),
+        DateTime::from_string("2020-05-01T14:00:00+02:00")
+    );
+    assert_eq!(
+        DateTime::parse("Fri, 01 May 2020 14:00:00 +0200", &utc),
+        DateTime::from_string("2020-05-01T14:00:00+02:00")
+    );
+    assert_eq!(DateTime::parse("yesterday", &utc), None);
+
+    for input in DATE_FORMATS {
+        assert!(DateTime::parse(input, &utc).is_some(), "{}", input);
+    }
+}
+
+#[test]
+fn test_parse_utc_offset() {
+    assert_eq!(parse_utc_offset("UTC"), Some(FixedOffset::east(0)));
+    assert_eq!(parse_utc_offset("+02:00"), Some(FixedOffset::east(7200)));
+    assert_eq!(parse_utc_offset("-0130"), Some(FixedOffset::west(5400)));
+    assert_eq!(parse_utc_offset("02:00"), None);
 }
 
+#[allow(dead_code)]
 pub trait FromRaw
 where
     Self: 'static + Sized,
@@ -128,7 +309,7 @@ impl FromRaw for bool {
 
 impl FromRaw for DateTime {
     fn from_raw(raw: &str) -> Option<DateTime> {
-        DateTime::from_string(raw.trim())
+        DateTime::parse(raw, &FixedOffset::east(0))
     }
 }
 
@@ -140,7 +321,7 @@ impl FromRaw for String {
 
 impl FromRaw for Vec<String> {
     fn from_raw(raw: &str) -> Option<Vec<String>> {
-        Some(raw.split(',').flat_map(|v| FromRaw::from_raw(v)).collect())
+        Some(raw.split(',').flat_map(FromRaw::from_raw).collect())
     }
 }
 